@@ -1,42 +1,242 @@
+use std::{
+    collections::{BTreeSet, VecDeque},
+    fs, io,
+    ops::Range,
+    path::Path,
+};
+
+use crate::{
+    challenge::SAMPLE_CORPUS,
+    decryption::{key_from_plaintext, xor, AGREEMENT_THRESHOLDS, FULL_WEIGHT},
+    export::{Analysis, ExportFormat, UNKNOWN_MARKERS},
+    forge::{encrypt, forge, random_key},
+    hunt::{flag_prefixes, hunt, Crib},
+    keystream::{analyze, Pattern},
+    language::{guess_single_coverage, LanguageModel},
+    line_editor::LineEditor,
+    nonce::{keystream, nonce_groups, split_nonce, verify, StreamCipher, NONCE_LENGTHS},
+    plaintext::Alphabet,
+    propagation::{contradictions, neighbours, propagate, propagate_columns},
+    search::{compile, find_fits, find_matches, SearchMatch, SearchMode},
+    signatures::{plaintext_files, signature_cribs, PlaintextFile},
+    solver::SolverState,
+    statistics::{column_stats, ColumnStats},
+    util::{decode_hex_line, decode_hex_lines, HexLines},
+};
+
+pub use crate::plaintext::KeyOrigin;
+
 pub enum Tab {
     Encrypted,
     Decryption,
+    Xor,
+    Statistics,
+    Forge,
 }
 
 pub enum InputMode {
     Normal,
     Editing,
+    KnownPlaintext,
+    Export,
+    Search,
+    Hunt,
+    BulkPaste,
+    Overwrite,
+    Signatures,
+    Verify,
+    Patterns,
+}
+
+const DEFAULT_FORGE_KEY_LEN: usize = 64;
+
+// Number of notifications kept for the history popup
+const MAX_NOTIFICATIONS: usize = 20;
+
+// Number of key edits that can be undone
+const MAX_UNDO: usize = 100;
+
+// Prefix the export prompt offers for the plaintext files
+pub const DEFAULT_FILES_PREFIX: &str = "mtpa-plaintext";
+
+// Change in a message's weight per key press, in percent
+pub const WEIGHT_STEP: u32 = 10;
+
+// Key bytes as they were before an edit: column, byte and origin
+type KeyEdit = Vec<(usize, Option<u8>, KeyOrigin)>;
+
+const DEFAULT_FLAG_PATTERN: &str = r"(flag|CTF)\{";
+
+// Field of the Forge tab that receives the typed input
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ForgeField {
+    Plaintext,
+    Key,
+    Forgery,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Level {
+    Info,
+    Error,
+}
+
+// A validation result or solver event shown in the status line
+pub struct Notification {
+    pub level: Level,
+    pub text: String,
 }
 
 pub struct App<'a> {
     pub titles: Vec<&'a str>,
     pub current_tab: Tab,
     pub input: String,
+    pub add_input: LineEditor,
+    // Index of the character that made the last Add input invalid
+    pub add_error: Option<usize>,
+    pub bulk_paste: HexLines,
     pub input_mode: InputMode,
     pub encrypted_messages: Vec<Vec<u8>>,
     pub decrypted_messages: Vec<Vec<u8>>,
+    pub disabled_messages: Vec<bool>,
+    // Trust in each message, in percent of a full vote
+    pub message_weights: Vec<u32>,
+    // Nonce or IV prefix stripped from each ciphertext, empty if none
+    pub nonces: Vec<Vec<u8>>,
+    // Length of the prefix to strip from ciphertexts as they are added
+    pub nonce_len: usize,
+    pub stream_cipher: StreamCipher,
+    pub selected_message: usize,
+    pub solver: SolverState,
+    pub language_model: LanguageModel,
     pub key: Vec<Option<u8>>,
+    pub key_origin: Vec<KeyOrigin>,
+    pub alphabet: Alphabet,
+    pub propagate: bool,
+    pub candidates: Vec<Vec<u8>>,
+    // Columns where propagation found no key byte that fits
+    pub contradictions: Vec<usize>,
+    pub export_format: ExportFormat,
+    pub export_error: Option<String>,
+    // The export prompt names the plaintext files instead of one export
+    pub write_files: bool,
+    pub unknown_marker: char,
+    pub search_pattern: String,
+    pub search_mode: SearchMode,
+    pub search_matches: Vec<SearchMatch>,
+    pub search_index: usize,
+    pub search_error: Option<String>,
+    pub flag_pattern: String,
+    pub cribs: Vec<Crib>,
+    pub crib_index: usize,
+    pub patterns: Vec<Pattern>,
+    pub pattern_index: usize,
+    pub hunt_error: Option<String>,
+    pub xor_pair: (usize, usize),
+    pub xor_column: usize,
+    pub stats_column: usize,
+    pub forge_field: ForgeField,
+    pub forge_key: Vec<u8>,
+    pub forge_plaintext: String,
+    pub forgery: Vec<Option<u8>>,
     pub position: (usize, usize),
+    // Hold typed key guesses until they are confirmed
+    pub preview_guesses: bool,
+    // Key bytes of the guess waiting for confirmation, one per byte of the
+    // typed character's UTF-8 encoding
+    pub pending_guess: Vec<(usize, u8)>,
+    // Columns [start, end) selected for overwriting; an empty selection takes
+    // the length of the replacement
+    pub overwrite_range: (usize, usize),
+    pub undo: Vec<KeyEdit>,
+    // Show plaintexts as hex and type plaintext bytes as two hex digits
+    pub binary: bool,
+    // First hex digit of a plaintext byte typed in binary mode
+    pub hex_digit: Option<u8>,
+    pub show_help: bool,
+    pub notifications: VecDeque<Notification>,
+    pub show_notifications: bool,
 }
 
 impl<'a> App<'a> {
     pub fn new() -> App<'a> {
         App {
-            titles: vec!["Encrypted", "Decryption"],
+            titles: vec!["Encrypted", "Decryption", "XOR", "Statistics", "Forge"],
             current_tab: Tab::Encrypted,
             input: String::new(),
+            add_input: LineEditor::default(),
+            add_error: None,
+            bulk_paste: HexLines::default(),
             input_mode: InputMode::Normal,
             encrypted_messages: Vec::new(),
             decrypted_messages: Vec::new(),
+            disabled_messages: Vec::new(),
+            message_weights: Vec::new(),
+            nonces: Vec::new(),
+            nonce_len: 0,
+            stream_cipher: StreamCipher::AesCtr,
+            selected_message: 0,
+            solver: SolverState::new(),
+            language_model: LanguageModel::new(SAMPLE_CORPUS),
             key: Vec::new(),
+            key_origin: Vec::new(),
+            alphabet: Alphabet::Text,
+            propagate: false,
+            candidates: Vec::new(),
+            contradictions: Vec::new(),
+            export_format: ExportFormat::KeyHex,
+            export_error: None,
+            write_files: false,
+            unknown_marker: '_',
+            search_pattern: String::new(),
+            search_mode: SearchMode::Regex,
+            search_matches: Vec::new(),
+            search_index: 0,
+            search_error: None,
+            flag_pattern: DEFAULT_FLAG_PATTERN.to_string(),
+            cribs: Vec::new(),
+            crib_index: 0,
+            patterns: Vec::new(),
+            pattern_index: 0,
+            hunt_error: None,
+            xor_pair: (0, 1),
+            xor_column: 0,
+            stats_column: 0,
+            forge_field: ForgeField::Plaintext,
+            forge_key: Vec::new(),
+            forge_plaintext: String::new(),
+            forgery: Vec::new(),
             position: (0, 0),
+            show_help: false,
+            notifications: VecDeque::new(),
+            show_notifications: false,
+            preview_guesses: false,
+            pending_guess: Vec::new(),
+            overwrite_range: (0, 0),
+            undo: Vec::new(),
+            binary: false,
+            hex_digit: None,
         }
     }
 
     pub fn toggle_tab(&mut self) {
         match self.current_tab {
             Tab::Encrypted => self.current_tab = Tab::Decryption,
+            Tab::Decryption => self.current_tab = Tab::Xor,
+            Tab::Xor => self.current_tab = Tab::Statistics,
+            Tab::Statistics => self.current_tab = Tab::Forge,
+            Tab::Forge => self.current_tab = Tab::Encrypted,
+        }
+        self.position = (0, 0);
+    }
+
+    pub fn toggle_tab_back(&mut self) {
+        match self.current_tab {
+            Tab::Encrypted => self.current_tab = Tab::Forge,
             Tab::Decryption => self.current_tab = Tab::Encrypted,
+            Tab::Xor => self.current_tab = Tab::Decryption,
+            Tab::Statistics => self.current_tab = Tab::Xor,
+            Tab::Forge => self.current_tab = Tab::Statistics,
         }
         self.position = (0, 0);
     }
@@ -45,6 +245,1082 @@ impl<'a> App<'a> {
         match self.current_tab {
             Tab::Encrypted => 0,
             Tab::Decryption => 1,
+            Tab::Xor => 2,
+            Tab::Statistics => 3,
+            Tab::Forge => 4,
+        }
+    }
+
+    pub fn notify(&mut self, level: Level, text: String) {
+        self.notifications.push_front(Notification { level, text });
+        self.notifications.truncate(MAX_NOTIFICATIONS);
+    }
+
+    // How much of the key is known, for notifications
+    pub fn key_summary(&self) -> String {
+        let known = self.key.iter().filter(|k| k.is_some()).count();
+        format!("{} of {} key bytes known", known, self.key.len())
+    }
+
+    // Record a new ciphertext and solve the columns it covers again
+    pub fn add_message(&mut self, msg_bytes: Vec<u8>) {
+        self.add_message_with_nonce(Vec::new(), msg_bytes);
+    }
+
+    pub fn add_message_with_nonce(&mut self, nonce: Vec<u8>, msg_bytes: Vec<u8>) {
+        let columns = self.solver.add(&msg_bytes, FULL_WEIGHT);
+        self.decrypted_messages.push(vec![0; msg_bytes.len()]);
+        self.disabled_messages.push(false);
+        self.message_weights.push(FULL_WEIGHT);
+        self.nonces.push(nonce);
+        self.encrypted_messages.push(msg_bytes);
+        self.sync_key(columns);
+    }
+
+    // Record a ciphertext as imported, stripping the nonce prefix if one is set
+    pub fn add_ciphertext(&mut self, ciphertext: Vec<u8>) {
+        let (nonce, msg_bytes) = split_nonce(&ciphertext, self.nonce_len);
+        self.add_message_with_nonce(nonce, msg_bytes);
+    }
+
+    pub fn next_nonce_len(&mut self) {
+        let i = NONCE_LENGTHS
+            .iter()
+            .position(|len| *len == self.nonce_len)
+            .map_or(0, |i| (i + 1) % NONCE_LENGTHS.len());
+        self.nonce_len = NONCE_LENGTHS[i];
+    }
+
+    // Messages sharing the nonce of message 'msg_id'
+    pub fn nonce_group(&self, msg_id: usize) -> Vec<usize> {
+        let groups = nonce_groups(&self.nonces);
+        (0..groups.len())
+            .filter(|i| groups[*i] == groups[msg_id])
+            .collect()
+    }
+
+    // Leave out every message encrypted under another nonce, as they use a
+    // different keystream, and bring back those under the selected one
+    pub fn isolate_nonce_group(&mut self) {
+        let group = self.nonce_group(self.selected_message);
+        for i in 0..self.encrypted_messages.len() {
+            if self.disabled_messages[i] == group.contains(&i) {
+                self.toggle_message(i);
+            }
+        }
+    }
+
+    // Compare the recovered key with the keystream the typed key gives for the
+    // nonce of the message under the cursor: agreeing and disagreeing bytes,
+    // first mismatch
+    pub fn verify_keystream(&self) -> Result<(usize, usize, Option<usize>), String> {
+        let test_key = decode_hex_line(&self.input)?;
+        let nonce = &self.nonces[self.position.0];
+        let expected = keystream(self.stream_cipher, &test_key, nonce, self.key.len())?;
+        Ok(verify(&self.key, &expected))
+    }
+
+    pub fn remove_message(&mut self, msg_id: usize) {
+        let msg_bytes = self.encrypted_messages.remove(msg_id);
+        self.decrypted_messages.remove(msg_id);
+        self.nonces.remove(msg_id);
+        let weight = self.message_weights.remove(msg_id);
+        let columns = match self.disabled_messages.remove(msg_id) {
+            true => 0..0,
+            false => self.solver.remove(&msg_bytes, weight),
+        };
+        self.selected_message = self
+            .selected_message
+            .min(self.encrypted_messages.len().saturating_sub(1));
+        self.sync_key(columns);
+    }
+
+    // Leave a message out of the solver without deleting it, or bring it back
+    pub fn toggle_message(&mut self, msg_id: usize) {
+        let disabled = !self.disabled_messages[msg_id];
+        self.disabled_messages[msg_id] = disabled;
+        let weight = self.message_weights[msg_id];
+        let columns = match disabled {
+            true => self.solver.remove(&self.encrypted_messages[msg_id], weight),
+            false => self.solver.add(&self.encrypted_messages[msg_id], weight),
+        };
+        self.sync_key(columns);
+    }
+
+    // Change how much the solver trusts a message
+    pub fn set_message_weight(&mut self, msg_id: usize, weight: u32) {
+        let old = std::mem::replace(&mut self.message_weights[msg_id], weight);
+        if self.disabled_messages[msg_id] {
+            return;
+        }
+        let msg_bytes = &self.encrypted_messages[msg_id];
+        self.solver.remove(msg_bytes, old);
+        let columns = self.solver.add(msg_bytes, weight);
+        self.sync_key(columns);
+    }
+
+    // Weigh every message by the share of its known bytes that decrypt into
+    // the alphabet, so the unreadable ones lose their say; messages with no
+    // known bytes keep their weight. Guessed bytes are left out, as they
+    // come from the text being judged. Returns the messages reweighed.
+    pub fn weigh_messages_by_readability(&mut self) -> usize {
+        let weights: Vec<Option<u32>> = self
+            .encrypted_messages
+            .iter()
+            .map(|msg| {
+                let known: Vec<u8> = msg
+                    .iter()
+                    .zip(self.key.iter().zip(&self.key_origin))
+                    .filter(|(_, (_, origin))| **origin != KeyOrigin::Guess)
+                    .filter_map(|(c, (k, _))| k.map(|k| c ^ k))
+                    .collect();
+                let readable = known.iter().filter(|p| self.alphabet.contains(**p)).count();
+                match known.is_empty() {
+                    true => None,
+                    false => Some((readable as u32 * FULL_WEIGHT) / known.len() as u32),
+                }
+            })
+            .collect();
+        let mut changed = 0;
+        for (i, weight) in weights.into_iter().enumerate() {
+            if let Some(weight) = weight.filter(|w| *w != self.message_weights[i]) {
+                self.set_message_weight(i, weight);
+                changed += 1;
+            }
+        }
+        changed
+    }
+
+    pub fn next_agreement_threshold(&mut self) {
+        let i = AGREEMENT_THRESHOLDS
+            .iter()
+            .position(|t| *t == self.solver.threshold())
+            .map_or(0, |i| (i + 1) % AGREEMENT_THRESHOLDS.len());
+        self.set_agreement_threshold(AGREEMENT_THRESHOLDS[i]);
+    }
+
+    pub fn set_agreement_threshold(&mut self, threshold: u32) {
+        let columns = self.solver.set_threshold(threshold);
+        self.sync_key(columns);
+    }
+
+    // Copy the solver output for 'columns' into the key. Manually set bytes are
+    // pinned and never overwritten by the solver. The edit history would put
+    // back stale solver bytes, so it is dropped.
+    fn sync_key(&mut self, columns: Range<usize>) {
+        self.undo.clear();
+        let max_len = self
+            .encrypted_messages
+            .iter()
+            .map(|m| m.len())
+            .max()
+            .unwrap_or(0);
+        self.key.resize(max_len, None);
+        self.key_origin.resize(max_len, KeyOrigin::Unknown);
+        let columns = columns.start..columns.end.min(max_len);
+        for j in columns.clone() {
+            if self.key_origin[j] == KeyOrigin::Manual {
+                continue;
+            }
+            self.key[j] = self.solver.key().get(j).copied().flatten();
+            self.key_origin[j] = match self.key[j] {
+                Some(_) => KeyOrigin::Solver,
+                None => KeyOrigin::Unknown,
+            };
+        }
+        self.refine_key(Some(&columns.collect::<Vec<usize>>()));
+    }
+
+    // Fill in what the solver leaves unknown: run the constraint propagation pass
+    // when enabled, then guess the columns only one message covers with the
+    // language model. Both are only redone around the 'changed' columns, or
+    // everywhere without them.
+    fn refine_key(&mut self, changed: Option<&[usize]>) {
+        let len = self.key.len();
+        if changed.is_none() || !self.propagate {
+            for j in 0..len {
+                if self.key_origin[j] == KeyOrigin::Propagated {
+                    self.key[j] = None;
+                    self.key_origin[j] = KeyOrigin::Unknown;
+                }
+            }
+        }
+        // Columns whose byte may have changed, other than guesses
+        let mut dirty: BTreeSet<usize> = match changed {
+            Some(changed) => changed.iter().copied().filter(|j| *j < len).collect(),
+            None => (0..len).collect(),
+        };
+        let enabled: Vec<bool> = self.disabled_messages.iter().map(|d| !d).collect();
+        self.candidates.resize(len, Vec::new());
+        if self.propagate {
+            let columns = changed.map(|changed| self.unpropagate(changed));
+            // Propagation works from the key without the guesses
+            let mut key: Vec<Option<u8>> = self
+                .key
+                .iter()
+                .zip(&self.key_origin)
+                .map(|(k, origin)| k.filter(|_| *origin != KeyOrigin::Guess))
+                .collect();
+            let fixed = match columns {
+                Some(columns) => {
+                    dirty.extend(&columns);
+                    propagate_columns(
+                        &self.encrypted_messages,
+                        &enabled,
+                        &mut key,
+                        self.alphabet,
+                        &mut self.candidates,
+                        columns,
+                    )
+                }
+                None => {
+                    let (candidates, fixed) =
+                        propagate(&self.encrypted_messages, &enabled, &mut key, self.alphabet);
+                    self.candidates = candidates;
+                    fixed
+                }
+            };
+            for j in fixed {
+                self.key[j] = key[j];
+                self.key_origin[j] = KeyOrigin::Propagated;
+                dirty.insert(j);
+            }
+            self.report_contradictions(&enabled);
+        } else {
+            self.candidates.iter_mut().for_each(|c| c.clear());
+            self.contradictions.clear();
+        }
+        self.reguess(&dirty, &enabled);
+        self.update_search();
+    }
+
+    // Guess again the single-coverage columns whose context may have changed:
+    // those in 'dirty' and the two after any column that changed, following
+    // on through the guesses
+    fn reguess(&mut self, dirty: &BTreeSet<usize>, enabled: &[bool]) {
+        let len = self.key.len();
+        let single: Vec<bool> = (0..len).map(|j| self.solver.coverage(j) == 1).collect();
+        let mut stale = vec![false; len];
+        for j in 0..len {
+            let moved = |i: usize| dirty.contains(&i) || (stale[i] && single[i]);
+            stale[j] = dirty.contains(&j) || (1..=2).any(|d| j >= d && moved(j - d));
+            if stale[j] && self.key_origin[j] == KeyOrigin::Guess {
+                self.key[j] = None;
+                self.key_origin[j] = KeyOrigin::Unknown;
+            }
+        }
+        let guessed: Vec<bool> = single.iter().zip(&stale).map(|(s, t)| *s && *t).collect();
+        let guesses = guess_single_coverage(
+            &self.language_model,
+            &self.encrypted_messages,
+            enabled,
+            &self.key,
+            &guessed,
+        );
+        for (pos, byte) in guesses {
+            self.key[pos] = Some(byte);
+            self.key_origin[pos] = KeyOrigin::Guess;
+        }
+    }
+
+    // Clear the propagated bytes that depended on the 'changed' columns, directly
+    // or through other propagated bytes; returns the columns to narrow again
+    fn unpropagate(&mut self, changed: &[usize]) -> BTreeSet<usize> {
+        let len = self.key.len();
+        let mut stack: Vec<usize> = changed.iter().copied().filter(|j| *j < len).collect();
+        let mut columns = BTreeSet::new();
+        while let Some(j) = stack.pop() {
+            for n in neighbours(j, len) {
+                if columns.insert(n) && self.key_origin[n] == KeyOrigin::Propagated {
+                    self.key[n] = None;
+                    self.key_origin[n] = KeyOrigin::Unknown;
+                    stack.push(n);
+                }
+            }
+        }
+        columns
+    }
+
+    // Warn about the columns propagation newly finds no key byte for
+    fn report_contradictions(&mut self, enabled: &[bool]) {
+        let found = contradictions(
+            &self.encrypted_messages,
+            enabled,
+            &self.key,
+            &self.candidates,
+        );
+        let new: Vec<String> = found
+            .iter()
+            .filter(|j| !self.contradictions.contains(j))
+            .map(|j| j.to_string())
+            .collect();
+        if !new.is_empty() {
+            let text = format!(
+                "No key byte fits column{} {}, a known byte next to {} may be wrong",
+                if new.len() == 1 { "" } else { "s" },
+                new.join(", "),
+                if new.len() == 1 { "it" } else { "them" }
+            );
+            self.notify(Level::Error, text);
+        }
+        self.contradictions = found;
+    }
+
+    // Search the plaintexts again, as they change with every key edit
+    pub fn update_search(&mut self) {
+        self.search_error = None;
+        self.search_matches = match self.search_mode {
+            _ if self.search_pattern.is_empty() => Vec::new(),
+            SearchMode::Regex => match compile(&self.search_pattern) {
+                Ok(regex) => find_matches(&regex, &self.encrypted_messages, &self.key),
+                Err(err) => {
+                    self.search_error = Some(err);
+                    Vec::new()
+                }
+            },
+            SearchMode::Fit => {
+                let enabled: Vec<bool> = self.disabled_messages.iter().map(|d| !d).collect();
+                find_fits(
+                    self.search_pattern.as_bytes(),
+                    &self.encrypted_messages,
+                    &enabled,
+                    &self.key,
+                    self.alphabet,
+                )
+            }
+        };
+        self.search_index = self
+            .search_index
+            .min(self.search_matches.len().saturating_sub(1));
+    }
+
+    pub fn clear_search(&mut self) {
+        self.search_pattern.clear();
+        self.update_search();
+    }
+
+    // Select the first match at or after the cursor
+    pub fn first_match(&mut self) {
+        let chars_before_msg = self.position.0.to_string().len() + 2;
+        let cursor = (
+            self.position.0,
+            self.position.1.saturating_sub(chars_before_msg),
+        );
+        self.search_index = self
+            .search_matches
+            .iter()
+            .position(|m| (m.message, m.start) >= cursor)
+            .unwrap_or(0);
+        self.move_to_match();
+    }
+
+    pub fn next_match(&mut self) {
+        if !self.search_matches.is_empty() {
+            self.search_index = (self.search_index + 1) % self.search_matches.len();
+            self.move_to_match();
         }
     }
+
+    pub fn previous_match(&mut self) {
+        if !self.search_matches.is_empty() {
+            let n = self.search_matches.len();
+            self.search_index = (self.search_index + n - 1) % n;
+            self.move_to_match();
+        }
+    }
+
+    // Put the editing cursor on the start of the selected match
+    fn move_to_match(&mut self) {
+        if let Some(m) = self.search_matches.get(self.search_index) {
+            self.position = (m.message, m.message.to_string().len() + 2 + m.start);
+        }
+    }
+
+    // Rank the placements of the flag prefixes against the current key
+    pub fn update_hunt(&mut self) {
+        self.hunt_error = None;
+        self.cribs = match flag_prefixes(&self.flag_pattern) {
+            Ok(prefixes) => {
+                let enabled: Vec<bool> = self.disabled_messages.iter().map(|d| !d).collect();
+                hunt(
+                    &prefixes,
+                    &self.encrypted_messages,
+                    &enabled,
+                    &self.key,
+                    self.alphabet,
+                    &self.language_model,
+                )
+            }
+            Err(err) => {
+                self.hunt_error = Some(err);
+                Vec::new()
+            }
+        };
+        self.crib_index = 0;
+    }
+
+    // Rank the placements of known file headers against the bytes set by
+    // hand. The solver and the guesses assume text, so they are overridden.
+    pub fn update_signatures(&mut self) {
+        let enabled: Vec<bool> = self.disabled_messages.iter().map(|d| !d).collect();
+        let manual: Vec<Option<u8>> = self
+            .key
+            .iter()
+            .zip(&self.key_origin)
+            .map(|(k, origin)| k.filter(|_| *origin == KeyOrigin::Manual))
+            .collect();
+        self.cribs = signature_cribs(&self.encrypted_messages, &enabled, &manual);
+        self.crib_index = 0;
+    }
+
+    // Look for periods, repeated blocks and LCG output in the key. Guesses
+    // are left out, so patterns can extend the key over them.
+    pub fn update_patterns(&mut self) {
+        let known: Vec<Option<u8>> = self
+            .key
+            .iter()
+            .zip(&self.key_origin)
+            .map(|(k, origin)| k.filter(|_| *origin != KeyOrigin::Guess))
+            .collect();
+        self.patterns = analyze(&known);
+        self.pattern_index = 0;
+    }
+
+    // Set the bytes the selected pattern predicts as one undoable edit;
+    // returns how many were set
+    pub fn accept_pattern(&mut self) -> usize {
+        let patterns = std::mem::take(&mut self.patterns);
+        let bytes: Vec<(usize, Option<u8>)> =
+            patterns
+                .get(self.pattern_index)
+                .map_or(Vec::new(), |pattern| {
+                    pattern
+                        .extension
+                        .iter()
+                        .map(|(pos, byte)| (*pos, Some(*byte)))
+                        .collect()
+                });
+        self.set_manual_key_bytes(&bytes);
+        bytes.len()
+    }
+
+    // The plaintexts whose confirmed key bytes cover enough of them to be
+    // written out. The solver and the guesses assume text, so a file made
+    // of their bytes would be invented.
+    pub fn plaintext_files(&self) -> Vec<PlaintextFile> {
+        let confirmed: Vec<Option<u8>> = self
+            .key
+            .iter()
+            .zip(&self.key_origin)
+            .map(|(k, origin)| k.filter(|_| origin.is_confirmed()))
+            .collect();
+        plaintext_files(&self.encrypted_messages, &confirmed)
+    }
+
+    // Write the plaintexts known well enough as files named after the prefix
+    // in 'input', their message and detected format. Refuses to overwrite
+    // anything; returns the paths written and the unknown bytes left as zeros.
+    pub fn write_plaintext_files(&self) -> io::Result<(Vec<String>, usize)> {
+        let files = self.plaintext_files();
+        let paths: Vec<String> = files
+            .iter()
+            .map(|file| format!("{}-{}.{}", self.input, file.message, file.extension))
+            .collect();
+        // Nothing is written unless every file is new
+        if let Some(path) = paths.iter().find(|path| Path::new(path).exists()) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", path),
+            ));
+        }
+        for (file, path) in files.iter().zip(&paths) {
+            fs::write(path, &file.plaintext)?;
+        }
+        Ok((paths, files.iter().map(|file| file.unknown).sum()))
+    }
+
+    // Commit the selected crib into the key
+    pub fn accept_crib(&mut self) {
+        let cribs = std::mem::take(&mut self.cribs);
+        if let Some(crib) = cribs.get(self.crib_index) {
+            let bytes: Vec<(usize, Option<u8>)> = crib
+                .key
+                .iter()
+                .map(|(pos, byte)| (*pos, Some(*byte)))
+                .collect();
+            self.set_manual_key_bytes(&bytes);
+            self.position = (
+                crib.message,
+                crib.message.to_string().len() + 2 + crib.offset,
+            );
+        }
+    }
+
+    pub fn next_search_mode(&mut self) {
+        self.search_mode = self.search_mode.next();
+        self.update_search();
+    }
+
+    // Hold pasted lines for review before adding them as ciphertexts
+    pub fn start_bulk_paste(&mut self, text: &str) {
+        self.bulk_paste = decode_hex_lines(text);
+        self.input_mode = InputMode::BulkPaste;
+    }
+
+    pub fn commit_bulk_paste(&mut self) {
+        for ciphertext in std::mem::take(&mut self.bulk_paste).accepted {
+            self.add_ciphertext(ciphertext);
+        }
+    }
+
+    // Encrypt the typed plaintext with the Forge key and record the ciphertext
+    pub fn encrypt_input(&mut self) {
+        let ciphertext = encrypt(self.input.as_bytes(), &mut self.forge_key);
+        self.add_message(ciphertext);
+    }
+
+    pub fn generate_forge_key(&mut self) {
+        self.forge_key = random_key(self.forge_key.len().max(DEFAULT_FORGE_KEY_LEN));
+    }
+
+    // Forge a ciphertext for the typed plaintext using the recovered key
+    pub fn forge_input(&mut self) {
+        self.forgery = forge(self.input.as_bytes(), &self.key);
+        self.forge_plaintext = self.input.clone();
+    }
+
+    // Set (or clear) a single key byte by hand
+    pub fn set_manual_key_byte(&mut self, pos: usize, byte: Option<u8>) {
+        self.set_manual_key_bytes(&[(pos, byte)]);
+    }
+
+    // Set (or clear) key bytes by hand as a single edit that can be undone
+    pub fn set_manual_key_bytes(&mut self, bytes: &[(usize, Option<u8>)]) {
+        if bytes.is_empty() {
+            return;
+        }
+        let edit = bytes
+            .iter()
+            .map(|(pos, _)| (*pos, self.key[*pos], self.key_origin[*pos]))
+            .collect();
+        if self.undo.len() == MAX_UNDO {
+            self.undo.remove(0);
+        }
+        self.undo.push(edit);
+        for (pos, byte) in bytes {
+            self.key[*pos] = *byte;
+            self.key_origin[*pos] = match byte {
+                Some(_) => KeyOrigin::Manual,
+                None => KeyOrigin::Unknown,
+            };
+        }
+        let columns: Vec<usize> = bytes.iter().map(|(pos, _)| *pos).collect();
+        self.refine_key(Some(&columns));
+    }
+
+    // Put back the key bytes of the last edit; returns how many there were.
+    // Changing the messages, their weights or the agreement threshold changes
+    // the columns the solver fills in, so it forgets every edit.
+    pub fn undo_key_edit(&mut self) -> Option<usize> {
+        let edit = self.undo.pop()?;
+        for (pos, byte, origin) in edit.iter().rev() {
+            if *pos < self.key.len() {
+                self.key[*pos] = *byte;
+                self.key_origin[*pos] = *origin;
+            }
+        }
+        let columns: Vec<usize> = edit.iter().map(|(pos, _, _)| *pos).collect();
+        self.refine_key(Some(&columns));
+        Some(edit.len())
+    }
+
+    // Parse the known plaintext being typed: an optional "@<offset> " prefix
+    // followed by the plaintext itself
+    pub fn known_plaintext(&self) -> (usize, &[u8]) {
+        if let Some(rest) = self.input.strip_prefix('@') {
+            if let Some((offset, text)) = rest.split_once(' ') {
+                if let Ok(offset) = offset.parse() {
+                    return (offset, text.as_bytes());
+                }
+            }
+        }
+        (0, self.input.as_bytes())
+    }
+
+    // Key bytes implied by the known plaintext for the selected message
+    pub fn known_plaintext_key(&self) -> Vec<(usize, u8)> {
+        let (offset, plaintext) = self.known_plaintext();
+        match self.encrypted_messages.get(self.position.0) {
+            Some(ciphertext) => key_from_plaintext(ciphertext, plaintext, offset),
+            None => Vec::new(),
+        }
+    }
+
+    // Current key with the pending known plaintext applied on top of it
+    pub fn preview_key(&self) -> (Vec<Option<u8>>, Vec<KeyOrigin>) {
+        let mut key = self.key.clone();
+        let mut key_origin = self.key_origin.clone();
+        let pending = match self.input_mode {
+            InputMode::KnownPlaintext => self.known_plaintext_key(),
+            InputMode::Hunt | InputMode::Signatures => self
+                .cribs
+                .get(self.crib_index)
+                .map_or(Vec::new(), |crib| crib.key.clone()),
+            InputMode::Editing => self.pending_guess.clone(),
+            InputMode::Overwrite => self.overwrite_key().unwrap_or_default(),
+            InputMode::Patterns => self
+                .patterns
+                .get(self.pattern_index)
+                .map_or(Vec::new(), |pattern| pattern.extension.clone()),
+            _ => Vec::new(),
+        };
+        for (pos, byte) in pending {
+            key[pos] = Some(byte);
+            key_origin[pos] = KeyOrigin::Manual;
+        }
+        (key, key_origin)
+    }
+
+    // Count the other messages touched by the pending known plaintext and how
+    // many of them still decrypt into the alphabet
+    pub fn known_plaintext_report(&self) -> (usize, usize) {
+        let derived = self.known_plaintext_key();
+        let (mut readable, mut covered) = (0, 0);
+        for (i, msg) in self.encrypted_messages.iter().enumerate() {
+            if i == self.position.0 {
+                continue;
+            }
+            let bytes: Vec<u8> = derived
+                .iter()
+                .filter(|(pos, _)| *pos < msg.len())
+                .map(|(pos, byte)| msg[*pos] ^ byte)
+                .collect();
+            if !bytes.is_empty() {
+                covered += 1;
+                if bytes.iter().all(|c| self.alphabet.contains(*c)) {
+                    readable += 1;
+                }
+            }
+        }
+        (readable, covered)
+    }
+
+    // Column of the message byte under the cursor while editing the key
+    pub fn cursor_column(&self) -> Option<usize> {
+        let msg_id = self.position.0;
+        let msg = self.encrypted_messages.get(msg_id)?;
+        self.position
+            .1
+            .checked_sub(msg_id.to_string().len() + 2)
+            .filter(|j| *j < msg.len())
+    }
+
+    // Key bytes that make the character under the cursor read as 'c'. A
+    // non-ASCII character sets one byte per byte of its UTF-8 encoding.
+    pub fn guess_key(&self, c: char) -> Result<Vec<(usize, u8)>, String> {
+        let column = self
+            .cursor_column()
+            .ok_or("the cursor is not on a message byte")?;
+        let msg = &self.encrypted_messages[self.position.0];
+        let mut buffer = [0; 4];
+        let plaintext = c.encode_utf8(&mut buffer).as_bytes();
+        if column + plaintext.len() > msg.len() {
+            return Err(format!(
+                "{:?} takes {} bytes but message {} ends after {}",
+                c,
+                plaintext.len(),
+                self.position.0,
+                msg.len() - column
+            ));
+        }
+        Ok(key_from_plaintext(msg, plaintext, column))
+    }
+
+    // Binary mode counterpart of 'guess_key': the first hex digit is held, and
+    // the second gives the plaintext byte under the cursor
+    pub fn hex_guess_key(&mut self, c: char) -> Result<Option<Vec<(usize, u8)>>, String> {
+        let column = self
+            .cursor_column()
+            .ok_or("the cursor is not on a message byte")?;
+        let digit = c
+            .to_digit(16)
+            .ok_or_else(|| format!("{:?} is not a hex digit", c))? as u8;
+        match self.hex_digit.take() {
+            None => {
+                self.hex_digit = Some(digit);
+                Ok(None)
+            }
+            Some(high) => {
+                let msg = &self.encrypted_messages[self.position.0];
+                Ok(Some(key_from_plaintext(msg, &[high << 4 | digit], column)))
+            }
+        }
+    }
+
+    // Count the other enabled messages the pending guess decrypts into the
+    // alphabet and out of it
+    pub fn pending_guess_report(&self) -> (usize, usize) {
+        let (mut valid, mut invalid) = (0, 0);
+        for (i, msg) in self.encrypted_messages.iter().enumerate() {
+            let covered: Vec<u8> = self
+                .pending_guess
+                .iter()
+                .filter(|(pos, _)| *pos < msg.len())
+                .map(|(pos, byte)| msg[*pos] ^ byte)
+                .collect();
+            if i == self.position.0 || self.disabled_messages[i] || covered.is_empty() {
+                continue;
+            }
+            match covered.iter().all(|c| self.alphabet.contains(*c)) {
+                true => valid += 1,
+                false => invalid += 1,
+            }
+        }
+        (valid, invalid)
+    }
+
+    // Commit the pending known plaintext into the key
+    pub fn apply_known_plaintext(&mut self) {
+        let bytes: Vec<(usize, Option<u8>)> = self
+            .known_plaintext_key()
+            .into_iter()
+            .map(|(pos, byte)| (pos, Some(byte)))
+            .collect();
+        self.set_manual_key_bytes(&bytes);
+    }
+
+    // Start overwriting the message under the cursor from the cursor column
+    pub fn start_overwrite(&mut self) {
+        if let Some(column) = self.cursor_column() {
+            self.overwrite_range = (column, column);
+            self.pending_guess.clear();
+            self.input.clear();
+            self.input_mode = InputMode::Overwrite;
+        }
+    }
+
+    // Key bytes that make the selected span read as the typed replacement
+    pub fn overwrite_key(&self) -> Result<Vec<(usize, u8)>, String> {
+        let msg_id = self.position.0;
+        let msg = &self.encrypted_messages[msg_id];
+        let (start, end) = self.overwrite_range;
+        let text = self.input.as_bytes();
+        if end > start && end - start != text.len() {
+            return Err(format!(
+                "the replacement is {} bytes but the selection is {}",
+                text.len(),
+                end - start
+            ));
+        }
+        if start + text.len() > msg.len() {
+            return Err(format!(
+                "the replacement runs {} bytes past the end of message {}",
+                start + text.len() - msg.len(),
+                msg_id
+            ));
+        }
+        Ok(key_from_plaintext(msg, text, start))
+    }
+
+    // Columns and messages the key bytes would decrypt outside the alphabet
+    pub fn overwrite_conflicts(&self, key: &[(usize, u8)]) -> Vec<(usize, usize)> {
+        let mut conflicts = Vec::new();
+        for (i, msg) in self.encrypted_messages.iter().enumerate() {
+            if i == self.position.0 || self.disabled_messages[i] {
+                continue;
+            }
+            for (pos, byte) in key.iter().filter(|(pos, _)| *pos < msg.len()) {
+                if !self.alphabet.contains(msg[*pos] ^ byte) {
+                    conflicts.push((i, *pos));
+                }
+            }
+        }
+        conflicts
+    }
+
+    // Set every key byte of the overwrite at once. It is refused when other
+    // messages would leave the alphabet, unless forced.
+    pub fn apply_overwrite(&mut self, force: bool) -> Result<usize, String> {
+        if self.input.is_empty() {
+            return Err("there is no replacement text".to_string());
+        }
+        let key = self.overwrite_key()?;
+        let conflicts = self.overwrite_conflicts(&key);
+        if !conflicts.is_empty() && !force {
+            let (msg_id, column) = conflicts[0];
+            return Err(format!(
+                "{} bytes of other messages would leave the alphabet, first in message {} column {}",
+                conflicts.len(),
+                msg_id,
+                column
+            ));
+        }
+        let bytes: Vec<(usize, Option<u8>)> =
+            key.iter().map(|(pos, byte)| (*pos, Some(*byte))).collect();
+        self.set_manual_key_bytes(&bytes);
+        Ok(key.len())
+    }
+
+    pub fn analysis(&self) -> Analysis<'_> {
+        Analysis {
+            encrypted_messages: &self.encrypted_messages,
+            key: &self.key,
+            key_origin: &self.key_origin,
+            unknown_marker: self.unknown_marker,
+        }
+    }
+
+    // Write the analysis in the selected format to the path typed in the input box
+    pub fn export(&self) -> io::Result<()> {
+        let exported = self
+            .analysis()
+            .export(self.export_format)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        fs::write(&self.input, exported)
+    }
+
+    // Step through the unknown markers, forwards or backwards
+    pub fn next_unknown_marker(&mut self, forward: bool) {
+        let n = UNKNOWN_MARKERS.len();
+        self.unknown_marker = match UNKNOWN_MARKERS
+            .iter()
+            .position(|m| *m == self.unknown_marker)
+        {
+            Some(i) if forward => UNKNOWN_MARKERS[(i + 1) % n],
+            Some(i) => UNKNOWN_MARKERS[(i + n - 1) % n],
+            None => UNKNOWN_MARKERS[0],
+        };
+    }
+
+    pub fn default_export_path(&self) -> String {
+        format!("mtpa-export.{}", self.export_format.extension())
+    }
+
+    pub fn toggle_propagation(&mut self) {
+        self.propagate = !self.propagate;
+        self.refine_key(None);
+    }
+
+    pub fn next_alphabet(&mut self) {
+        self.alphabet = self.alphabet.next();
+        self.refine_key(None);
+    }
+
+    // XOR of the two messages picked in the XOR tab, if both exist
+    pub fn xor_pair_bytes(&self) -> Option<Vec<u8>> {
+        let (a, b) = self.xor_pair;
+        match (
+            self.encrypted_messages.get(a),
+            self.encrypted_messages.get(b),
+        ) {
+            (Some(a), Some(b)) => Some(xor(a, b)),
+            _ => None,
+        }
+    }
+
+    // Open the Decryption editor on the first message of the XOR pair, at the
+    // column under the XOR cursor
+    pub fn jump_to_xor_column(&mut self) {
+        let msg_id = self.xor_pair.0;
+        self.current_tab = Tab::Decryption;
+        self.input_mode = InputMode::Editing;
+        self.position = (msg_id, msg_id.to_string().len() + 2 + self.xor_column);
+    }
+
+    pub fn column_stats(&self, j: usize) -> ColumnStats {
+        let column: Vec<u8> = self
+            .encrypted_messages
+            .iter()
+            .zip(&self.disabled_messages)
+            .filter(|(m, disabled)| !**disabled && m.len() > j)
+            .map(|(m, _)| m[j])
+            .collect();
+        column_stats(
+            self.solver.column(j),
+            &column,
+            self.key.get(j).copied().flatten(),
+            &self.language_model,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::challenge::{generate, ChallengeConfig, LengthDistribution};
+
+    #[test]
+    fn test_overwrite_and_undo() {
+        let true_key = [0x31, 0x42, 0x53, 0x64, 0x75];
+        let mut app = App::new();
+        app.propagate = false;
+        for plaintext in [b"hello".as_slice(), b"world", b"xor!!"] {
+            app.add_message(xor(plaintext, &true_key));
+        }
+        let before = app.key.clone();
+        app.position = (0, 3);
+        app.start_overwrite();
+        app.input = "hel".to_string();
+        assert_eq!(app.overwrite_key().unwrap().len(), 3);
+        app.overwrite_range.1 = 2;
+        assert!(app.overwrite_key().is_err());
+        app.overwrite_range.1 = 0;
+        app.input.clear();
+        assert!(app.apply_overwrite(true).is_err());
+
+        // "h\x01l" would put a control character in the other messages
+        app.input = "h\u{1}l".to_string();
+        assert!(app.apply_overwrite(false).is_err());
+        app.input = "hel".to_string();
+        assert_eq!(app.apply_overwrite(false), Ok(3));
+        assert_eq!(&app.key[..3], &true_key.map(Some)[..3]);
+
+        // One undo puts back all three bytes
+        assert_eq!(app.undo_key_edit(), Some(3));
+        assert_eq!(app.key, before);
+        assert_eq!(app.undo_key_edit(), None);
+
+        // Edits made before a message is added cannot be undone after it
+        assert_eq!(app.apply_overwrite(false), Ok(3));
+        app.add_message(xor(b"abcde", &true_key));
+        assert_eq!(app.undo_key_edit(), None);
+
+        // Nor after a message is left out or reweighed
+        assert_eq!(app.apply_overwrite(false), Ok(3));
+        app.toggle_message(1);
+        assert_eq!(app.undo_key_edit(), None);
+        assert_eq!(app.apply_overwrite(false), Ok(3));
+        app.set_message_weight(2, 50);
+        assert_eq!(app.undo_key_edit(), None);
+    }
+
+    #[test]
+    fn test_incremental_refinement() {
+        let config = ChallengeConfig {
+            messages: 12,
+            lengths: LengthDistribution::Uniform(30, 70),
+            alphabet: Alphabet::Letters,
+            seed: 7,
+        };
+        let challenge = generate(SAMPLE_CORPUS, &config).unwrap();
+        let mut app = App::new();
+        app.alphabet = Alphabet::Letters;
+        for ciphertext in challenge.ciphertexts {
+            app.add_message(ciphertext);
+        }
+        app.toggle_propagation();
+
+        // Set right and wrong bytes and clear some, then check that narrowing
+        // and guessing the columns around each edit matches a full pass. Only
+        // one message covers the last columns, so they are guessed.
+        let n = challenge.key.len();
+        assert!(app.key_origin[n - 3..]
+            .iter()
+            .all(|o| *o == KeyOrigin::Guess));
+        let edits = [
+            (3, Some(challenge.key[3])),
+            (10, None),
+            (11, None),
+            (12, None),
+            (13, None),
+            (30, Some(challenge.key[30] ^ 0x20)),
+            (31, None),
+            (30, None),
+            (45, Some(challenge.key[45])),
+            (n - 6, None),
+            (n - 3, Some(challenge.key[n - 3] ^ 1)),
+            (n - 3, None),
+            (n - 5, Some(challenge.key[n - 5])),
+        ];
+        for (pos, byte) in edits {
+            app.set_manual_key_byte(pos, byte);
+            let incremental = (
+                app.key.clone(),
+                app.key_origin.clone(),
+                app.candidates.clone(),
+            );
+            app.refine_key(None);
+            assert_eq!(
+                incremental,
+                (
+                    app.key.clone(),
+                    app.key_origin.clone(),
+                    app.candidates.clone()
+                )
+            );
+        }
+        app.undo_key_edit();
+        let incremental = (
+            app.key.clone(),
+            app.key_origin.clone(),
+            app.candidates.clone(),
+        );
+        app.refine_key(None);
+        assert_eq!(incremental, (app.key, app.key_origin, app.candidates));
+    }
+
+    #[test]
+    fn test_plaintext_files_confirmed() {
+        let true_key: Vec<u8> = (0..40).map(|i| (i * 37 + 11) as u8).collect();
+        let mut app = App::new();
+        app.add_message(xor(&[0x89; 40], &true_key));
+        app.add_message(xor(&[0x17; 40], &true_key));
+
+        // Bytes guessed or voted for text never make a file on their own
+        app.key = true_key.iter().copied().map(Some).collect();
+        for origin in [KeyOrigin::Guess, KeyOrigin::Solver] {
+            app.key_origin = vec![origin; 40];
+            assert!(app.plaintext_files().is_empty());
+        }
+        app.key_origin[..36].fill(KeyOrigin::Manual);
+        app.key_origin[36..].fill(KeyOrigin::Guess);
+        let files = app.plaintext_files();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].unknown, 4);
+        assert_eq!(&files[0].plaintext[36..], &[0; 4]);
+    }
+
+    #[test]
+    fn test_weighted_agreement() {
+        let key = 0x5a;
+        let mut app = App::new();
+        for plaintext in [b" ", b" ", b" ", b" ", b"~"] {
+            app.add_message(vec![plaintext[0] ^ key]);
+        }
+        // The noisy last message blocks a unanimous vote
+        assert_eq!(app.key, vec![None]);
+        app.set_agreement_threshold(80);
+        assert_eq!(app.key, vec![Some(key)]);
+
+        app.set_agreement_threshold(90);
+        assert_eq!(app.key, vec![None]);
+        app.set_message_weight(4, 20);
+        assert_eq!(app.key, vec![Some(key)]);
+
+        // Disabled and removed messages leave with the weight they had
+        app.toggle_message(4);
+        app.set_message_weight(4, 50);
+        app.toggle_message(4);
+        app.remove_message(4);
+        app.set_agreement_threshold(100);
+        assert_eq!(app.key, vec![Some(key)]);
+    }
+
+    #[test]
+    fn test_verify_keystream_at_cursor() {
+        let mut app = App::new();
+        app.add_message_with_nonce(vec![0; 8], vec![0; 4]);
+        app.add_message_with_nonce(vec![1; 8], vec![0; 4]);
+        app.stream_cipher = StreamCipher::ChaCha20;
+        app.input = "00".repeat(32);
+        let expected = keystream(StreamCipher::ChaCha20, &[0; 32], &[1; 8], 4).unwrap();
+        app.key = expected.into_iter().map(Some).collect();
+
+        // The message under the cursor, not the one selected on the Encrypted
+        // tab, gives the nonce
+        app.position = (1, 0);
+        assert_eq!(app.verify_keystream(), Ok((4, 0, None)));
+        app.position = (0, 0);
+        assert_ne!(app.verify_keystream(), Ok((4, 0, None)));
+    }
 }
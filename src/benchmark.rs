@@ -0,0 +1,260 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    challenge::{
+        generate, score_key, ChallengeConfig, KeyScore, LengthDistribution, SAMPLE_CORPUS,
+    },
+    decryption::{decrypt_key, decrypt_key_pairwise, xor},
+    plaintext::Alphabet,
+    util::decode_hex,
+};
+
+// Average share of key bytes a solver must recover on the generated cases
+pub const MIN_RECOVERED: f64 = 0.5;
+// Maximum share of wrong key bytes a solver may produce on the generated cases
+pub const MAX_WRONG: f64 = 0.02;
+
+pub type Solver = fn(&[Vec<u8>]) -> Vec<Option<u8>>;
+
+pub const SOLVERS: &[(&str, Solver)] = &[
+    ("decrypt_key", decrypt_key),
+    ("pairwise", decrypt_key_pairwise),
+];
+
+// The ciphertexts of Dan Boneh's many-time pad exercise, whose last message is the target
+const DAN_BONEH_CIPHERTEXTS: &str = include_str!("../input/test.txt");
+const DAN_BONEH_TARGET: &[u8] =
+    b"The secret message is: When using a stream cipher, never use the key more than once";
+
+// A set of ciphertexts together with the key bytes we know to be correct
+pub struct BenchmarkCase {
+    pub name: String,
+    pub fixture: bool,
+    pub ciphertexts: Vec<Vec<u8>>,
+    pub key: Vec<u8>,
+}
+
+impl BenchmarkCase {
+    pub fn dan_boneh() -> BenchmarkCase {
+        let ciphertexts: Vec<Vec<u8>> = DAN_BONEH_CIPHERTEXTS
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| decode_hex(line).unwrap())
+            .collect();
+        let key = xor(ciphertexts.last().unwrap(), DAN_BONEH_TARGET);
+        BenchmarkCase {
+            name: "dan-boneh".to_string(),
+            fixture: true,
+            ciphertexts,
+            key,
+        }
+    }
+
+    pub fn generated(messages: usize, length: usize, seed: u64) -> BenchmarkCase {
+        let config = ChallengeConfig {
+            messages,
+            lengths: LengthDistribution::Fixed(length),
+            alphabet: Alphabet::Text,
+            seed,
+        };
+        // The sample corpus covers the text alphabet
+        let challenge = generate(SAMPLE_CORPUS, &config).expect("sample corpus");
+        BenchmarkCase {
+            name: format!("generated-{}x{}", messages, length),
+            fixture: false,
+            ciphertexts: challenge.ciphertexts,
+            key: challenge.key,
+        }
+    }
+
+    pub fn messages(&self) -> usize {
+        self.ciphertexts.len()
+    }
+
+    pub fn length(&self) -> usize {
+        self.ciphertexts.iter().map(|c| c.len()).max().unwrap_or(0)
+    }
+}
+
+// Generated cases for every combination of message count and length
+pub fn generated_cases(messages: &[usize], lengths: &[usize]) -> Vec<BenchmarkCase> {
+    let mut cases = Vec::new();
+    for &n in messages {
+        for &len in lengths {
+            cases.push(BenchmarkCase::generated(n, len, (n * 1000 + len) as u64));
+        }
+    }
+    cases
+}
+
+pub struct BenchmarkResult {
+    pub solver: &'static str,
+    pub case: String,
+    pub fixture: bool,
+    pub messages: usize,
+    pub length: usize,
+    pub score: KeyScore,
+    pub runtime: Duration,
+    pub operations: usize,
+}
+
+// Bytes a solver looks at to fill in the key columns: the column-wise solver
+// tallies every ciphertext byte once, the pairwise one XORs every ordered pair
+// of messages over the columns they all share, dropping the shortest after
+// each chunk
+pub fn column_operations(solver: &str, ciphertexts: &[Vec<u8>]) -> usize {
+    match solver {
+        "pairwise" => {
+            let mut lengths: Vec<usize> = ciphertexts.iter().map(|c| c.len()).collect();
+            lengths.sort();
+            let mut operations = 0;
+            let mut done = 0;
+            for (i, &len) in lengths.iter().enumerate() {
+                let remaining = lengths.len() - i;
+                if remaining < 2 {
+                    break;
+                }
+                operations += remaining * (remaining - 1) * (len - done);
+                done = len;
+            }
+            operations
+        }
+        _ => ciphertexts.iter().map(|c| c.len()).sum(),
+    }
+}
+
+pub fn run_benchmark(
+    solvers: &[(&'static str, Solver)],
+    cases: &[BenchmarkCase],
+) -> Vec<BenchmarkResult> {
+    let mut results = Vec::new();
+    for (solver_name, solver) in solvers {
+        for case in cases {
+            let start = Instant::now();
+            let key = solver(&case.ciphertexts);
+            let runtime = start.elapsed();
+            results.push(BenchmarkResult {
+                solver: solver_name,
+                case: case.name.clone(),
+                fixture: case.fixture,
+                messages: case.messages(),
+                length: case.length(),
+                score: score_key(&key, &case.key),
+                runtime,
+                operations: column_operations(solver_name, &case.ciphertexts),
+            });
+        }
+    }
+    results
+}
+
+// Recovered and wrong shares of a solver averaged over the generated cases
+pub fn average_score(results: &[BenchmarkResult], solver: &str) -> (f64, f64) {
+    let results: Vec<&BenchmarkResult> = results
+        .iter()
+        .filter(|r| r.solver == solver && !r.fixture)
+        .collect();
+    let n = results.len().max(1) as f64;
+    let recovered = results
+        .iter()
+        .map(|r| r.score.recovered_fraction())
+        .sum::<f64>()
+        / n;
+    let wrong = results
+        .iter()
+        .map(|r| r.score.wrong_fraction())
+        .sum::<f64>()
+        / n;
+    (recovered, wrong)
+}
+
+// Time a solver spent on every case, fixtures included
+pub fn total_runtime(results: &[BenchmarkResult], solver: &str) -> Duration {
+    results
+        .iter()
+        .filter(|r| r.solver == solver)
+        .map(|r| r.runtime)
+        .sum()
+}
+
+// Column operations of a solver over every case, fixtures included
+pub fn total_operations(results: &[BenchmarkResult], solver: &str) -> usize {
+    results
+        .iter()
+        .filter(|r| r.solver == solver)
+        .map(|r| r.operations)
+        .sum()
+}
+
+pub fn format_results(results: &[BenchmarkResult]) -> String {
+    let mut table = format!(
+        "{:<14} {:<20} {:>8} {:>6} {:>10} {:>8} {:>12}\n",
+        "solver", "case", "messages", "length", "recovered", "wrong", "runtime"
+    );
+    for r in results {
+        table.push_str(&format!(
+            "{:<14} {:<20} {:>8} {:>6} {:>9.1}% {:>7.1}% {:>12.3?}\n",
+            r.solver,
+            r.case,
+            r.messages,
+            r.length,
+            100.0 * r.score.recovered_fraction(),
+            100.0 * r.score.wrong_fraction(),
+            r.runtime
+        ));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solver_accuracy() {
+        let cases = generated_cases(&[8, 16], &[60, 120]);
+        let results = run_benchmark(SOLVERS, &cases);
+        for (solver, _) in SOLVERS {
+            let (recovered, wrong) = average_score(&results, solver);
+            assert!(recovered >= MIN_RECOVERED, "{}: {:.3}", solver, recovered);
+            assert!(wrong <= MAX_WRONG, "{}: {:.3}", solver, wrong);
+        }
+
+        let results = run_benchmark(SOLVERS, &[BenchmarkCase::dan_boneh()]);
+        for r in results {
+            assert!(r.score.correct >= 60, "{}: {:?}", r.solver, r.score);
+            assert!(r.score.wrong <= 1, "{}: {:?}", r.solver, r.score);
+        }
+    }
+
+    #[test]
+    fn test_column_wise_not_worse_than_pairwise() {
+        let cases = generated_cases(&[3, 5, 8, 12], &[40, 90]);
+        let results = run_benchmark(SOLVERS, &cases);
+        let (column_wise, _) = average_score(&results, "decrypt_key");
+        let (pairwise, _) = average_score(&results, "pairwise");
+        assert!(
+            column_wise >= pairwise,
+            "{:.3} < {:.3}",
+            column_wise,
+            pairwise
+        );
+    }
+
+    #[test]
+    fn test_column_wise_faster_than_pairwise() {
+        // Pairwise grows with the square of the message count, column-wise
+        // linearly
+        let cases = generated_cases(&[2, 10, 40], &[200]);
+        let results = run_benchmark(SOLVERS, &cases);
+        let column_wise = total_operations(&results, "decrypt_key");
+        let pairwise = total_operations(&results, "pairwise");
+        assert!(column_wise < pairwise, "{} >= {}", column_wise, pairwise);
+        assert_eq!(column_wise, (2 + 10 + 40) * 200);
+        assert_eq!(pairwise, (2 + 10 * 9 + 40 * 39) * 200);
+
+        let ciphertexts = vec![vec![0; 3], vec![0; 5], vec![0; 9]];
+        assert_eq!(column_operations("decrypt_key", &ciphertexts), 17);
+        assert_eq!(column_operations("pairwise", &ciphertexts), 6 * 3 + 2 * 2);
+    }
+}
@@ -0,0 +1,176 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{decryption::xor, plaintext::Alphabet};
+
+// A short English corpus to draw plaintexts from when no other is supplied
+pub const SAMPLE_CORPUS: &str = concat!(
+    "A one-time pad is perfectly secure as long as the key is truly random, at least as long ",
+    "as the message, kept secret and never used more than once. The moment the same pad is used ",
+    "to encrypt two different messages, the security collapses: the XOR of the two ciphertexts ",
+    "is equal to the XOR of the two plaintexts, and natural language has so much redundancy that ",
+    "both messages can usually be recovered. Spaces are especially useful, because the XOR of a ",
+    "space with a letter flips the case of that letter, which almost never happens by chance. ",
+    "During the Second World War, Soviet operators reused pages of their pads under the pressure ",
+    "of wartime production, and the Venona project exploited that mistake for decades. The same ",
+    "error keeps appearing in modern systems whenever a stream cipher is used with a fixed nonce, ",
+    "when a counter is reset after a crash, or when two devices share a seed. Careful engineers ",
+    "write down where every nonce comes from, test that it never repeats, and treat any reuse as ",
+    "a complete break of confidentiality. Students who try the attack by hand quickly learn to ",
+    "look for common words such as the, and, that or with, and to guess the rest from context. ",
+    "Once a few columns of the key are known, every other message reveals a little more of ",
+    "itself, and the whole puzzle unravels faster than anyone expects.",
+);
+
+// How the lengths of the generated plaintexts are chosen
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LengthDistribution {
+    Fixed(usize),
+    Uniform(usize, usize),
+}
+
+pub struct ChallengeConfig {
+    pub messages: usize,
+    pub lengths: LengthDistribution,
+    pub alphabet: Alphabet,
+    pub seed: u64,
+}
+
+// Plaintexts, key and ciphertexts of a generated many-time pad
+pub struct Challenge {
+    pub plaintexts: Vec<Vec<u8>>,
+    pub key: Vec<u8>,
+    pub ciphertexts: Vec<Vec<u8>>,
+}
+
+// Number of key bytes a solver got right, got wrong or left unknown
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct KeyScore {
+    pub correct: usize,
+    pub wrong: usize,
+    pub unknown: usize,
+}
+
+impl KeyScore {
+    pub fn total(&self) -> usize {
+        self.correct + self.wrong + self.unknown
+    }
+
+    pub fn recovered_fraction(&self) -> f64 {
+        self.correct as f64 / self.total().max(1) as f64
+    }
+
+    pub fn wrong_fraction(&self) -> f64 {
+        self.wrong as f64 / self.total().max(1) as f64
+    }
+}
+
+// Compare a recovered key against the true one
+pub fn score_key(recovered: &[Option<u8>], key: &[u8]) -> KeyScore {
+    let mut score = KeyScore::default();
+    for (i, byte) in key.iter().enumerate() {
+        match recovered.get(i).copied().flatten() {
+            Some(guess) if guess == *byte => score.correct += 1,
+            Some(_) => score.wrong += 1,
+            None => score.unknown += 1,
+        }
+    }
+    score
+}
+
+impl Challenge {
+    pub fn score(&self, recovered: &[Option<u8>]) -> KeyScore {
+        score_key(recovered, &self.key)
+    }
+}
+
+// Draw plaintexts from 'corpus', keeping only characters of the configured
+// alphabet, and encrypt them all with the same random key. Fails when the
+// corpus has no character of the alphabet to draw from or the length range
+// is empty.
+pub fn generate(corpus: &str, config: &ChallengeConfig) -> Result<Challenge, String> {
+    if let LengthDistribution::Uniform(min, max) = config.lengths {
+        if min > max {
+            return Err(format!("the length range {}-{} is empty", min, max));
+        }
+    }
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut text: Vec<u8> = Vec::new();
+    for c in corpus.bytes() {
+        let c = if c.is_ascii_whitespace() { b' ' } else { c };
+        if config.alphabet.contains(c) && !(c == b' ' && text.last() == Some(&b' ')) {
+            text.push(c);
+        }
+    }
+    if text.is_empty() {
+        return Err(format!(
+            "the corpus has no character of the {} alphabet",
+            config.alphabet.name()
+        ));
+    }
+
+    let plaintexts: Vec<Vec<u8>> = (0..config.messages)
+        .map(|_| {
+            let len = match config.lengths {
+                LengthDistribution::Fixed(len) => len,
+                LengthDistribution::Uniform(min, max) => rng.gen_range(min..=max),
+            };
+            let start = rng.gen_range(0..text.len());
+            text.iter().cycle().skip(start).take(len).copied().collect()
+        })
+        .collect();
+
+    let key_len = plaintexts.iter().map(|p| p.len()).max().unwrap_or(0);
+    let mut key = vec![0; key_len];
+    rng.fill(key.as_mut_slice());
+    let ciphertexts = plaintexts.iter().map(|p| xor(p, &key)).collect();
+
+    Ok(Challenge {
+        plaintexts,
+        key,
+        ciphertexts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decryption::decrypt_key;
+
+    #[test]
+    fn test_generate() {
+        let config = ChallengeConfig {
+            messages: 8,
+            lengths: LengthDistribution::Uniform(20, 40),
+            alphabet: Alphabet::Letters,
+            seed: 7,
+        };
+        let challenge = generate(SAMPLE_CORPUS, &config).unwrap();
+        assert_eq!(challenge.ciphertexts.len(), 8);
+        for (plaintext, ciphertext) in challenge.plaintexts.iter().zip(&challenge.ciphertexts) {
+            assert!((20..=40).contains(&plaintext.len()));
+            assert!(plaintext.iter().all(|c| Alphabet::Letters.contains(*c)));
+            assert_eq!(&xor(ciphertext, &challenge.key), plaintext);
+        }
+        assert!(generate("0123456789", &config).is_err());
+
+        let config = ChallengeConfig {
+            lengths: LengthDistribution::Uniform(40, 20),
+            ..config
+        };
+        assert!(generate(SAMPLE_CORPUS, &config).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_key_recovery_rate() {
+        let config = ChallengeConfig {
+            messages: 12,
+            lengths: LengthDistribution::Fixed(80),
+            alphabet: Alphabet::Text,
+            seed: 42,
+        };
+        let challenge = generate(SAMPLE_CORPUS, &config).unwrap();
+        let score = challenge.score(&decrypt_key(&challenge.ciphertexts));
+        assert!(score.recovered_fraction() >= 0.7, "{:?}", score);
+        assert!(score.wrong_fraction() <= 0.02, "{:?}", score);
+    }
+}
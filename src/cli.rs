@@ -0,0 +1,247 @@
+use std::{
+    error::Error,
+    fs,
+    io::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    app_state::App,
+    benchmark::{
+        average_score, format_results, generated_cases, run_benchmark, total_operations,
+        total_runtime, BenchmarkCase, MAX_WRONG, MIN_RECOVERED, SOLVERS,
+    },
+    challenge::{generate, ChallengeConfig, LengthDistribution, SAMPLE_CORPUS},
+    decryption::{decrypt_key, UNANIMOUS},
+    export::ExportFormat,
+    plaintext::Alphabet,
+    util::{decode_hex_lines, encode_hex},
+};
+
+const USAGE: &str = concat!(
+    "usage: mtpa <ciphertexts file> [--format <format>] [--unknown <char>] [--output <path>]\n",
+    "                               [--propagate] [--nonce <prefix length>]\n",
+    "                               [--agreement <percent>]\n",
+    "       mtpa generate [--messages <n>] [--length <n>|<min>-<max>] [--alphabet <name>]\n",
+    "                     [--seed <n>] [--corpus <file>] [--output <path>] [--key <path>]\n",
+    "                     [--plaintexts <path>]\n",
+    "       mtpa benchmark [--messages <n,...>] [--lengths <n,...>]"
+);
+
+// Read one hex encoded ciphertext per line, skipping blank lines
+pub fn load_ciphertexts(path: &str) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+    let lines = decode_hex_lines(&fs::read_to_string(path)?);
+    match lines.rejected.first() {
+        Some((n, _, err)) => Err(format!("{}:{}: {}", path, n, err).into()),
+        None => Ok(lines.accepted),
+    }
+}
+
+// Headless mode: solve the ciphertexts in a file and export the result
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    if args[0] == "generate" {
+        return generate_challenge(&args[1..]);
+    }
+    if args[0] == "benchmark" {
+        return benchmark(&args[1..]);
+    }
+
+    let mut input = None;
+    let mut format = ExportFormat::KeyHex;
+    let mut unknown_marker = '_';
+    let mut output = None;
+    let mut propagate = false;
+    let mut nonce_len = 0;
+    let mut agreement = UNANIMOUS;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--propagate" => propagate = true,
+            "--nonce" => nonce_len = args.next().ok_or(USAGE)?.parse()?,
+            "--agreement" => {
+                agreement = args.next().ok_or(USAGE)?.parse()?;
+                if !(1..=100).contains(&agreement) {
+                    return Err("the agreement must be a percentage from 1 to 100".into());
+                }
+            }
+            "--format" => {
+                let name = args.next().ok_or(USAGE)?;
+                format = ExportFormat::from_name(name).ok_or_else(|| {
+                    let names: Vec<&str> = ExportFormat::ALL.iter().map(|f| f.name()).collect();
+                    format!(
+                        "unknown format '{}', expected one of: {}",
+                        name,
+                        names.join(", ")
+                    )
+                })?;
+            }
+            "--unknown" => {
+                let marker = args.next().ok_or(USAGE)?;
+                let mut chars = marker.chars();
+                unknown_marker = match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    _ => return Err("the unknown marker must be a single character".into()),
+                };
+            }
+            "--output" => output = Some(args.next().ok_or(USAGE)?),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            path if input.is_none() && !path.starts_with("--") => input = Some(path),
+            _ => return Err(USAGE.into()),
+        }
+    }
+
+    let mut app = App::new();
+    app.nonce_len = nonce_len;
+    app.set_agreement_threshold(agreement);
+    for ciphertext in load_ciphertexts(input.ok_or(USAGE)?)? {
+        app.add_ciphertext(ciphertext);
+    }
+    // Messages under different nonces use different keystreams: solve the
+    // largest group that reuses one
+    if nonce_len > 0 && !app.encrypted_messages.is_empty() {
+        app.selected_message = (0..app.encrypted_messages.len())
+            .max_by_key(|i| (app.nonce_group(*i).len(), std::cmp::Reverse(*i)))
+            .unwrap_or(0);
+        app.isolate_nonce_group();
+    }
+    if propagate {
+        app.toggle_propagation();
+    }
+    app.unknown_marker = unknown_marker;
+
+    let exported = app.analysis().export(format)?;
+    match output {
+        Some(path) => fs::write(path, exported)?,
+        None => std::io::stdout().write_all(&exported)?,
+    }
+    Ok(())
+}
+
+fn parse_length(arg: &str) -> Result<LengthDistribution, Box<dyn Error>> {
+    match arg.split_once('-') {
+        Some((min, max)) => {
+            let (min, max) = (min.parse()?, max.parse()?);
+            if min > max {
+                return Err(format!("invalid length range '{}'", arg).into());
+            }
+            Ok(LengthDistribution::Uniform(min, max))
+        }
+        None => Ok(LengthDistribution::Fixed(arg.parse()?)),
+    }
+}
+
+// Headless mode: write a synthetic many-time pad and report how much of its
+// key the solver recovers
+fn generate_challenge(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut config = ChallengeConfig {
+        messages: 10,
+        lengths: LengthDistribution::Uniform(40, 80),
+        alphabet: Alphabet::Text,
+        seed: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+    };
+    let mut corpus = SAMPLE_CORPUS.to_string();
+    let mut output = None;
+    let mut key_output = None;
+    let mut plaintexts_output = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(USAGE);
+        match arg.as_str() {
+            "--messages" => config.messages = value()?.parse()?,
+            "--length" => config.lengths = parse_length(value()?)?,
+            "--alphabet" => {
+                let name = value()?;
+                config.alphabet = Alphabet::from_name(name)
+                    .ok_or_else(|| format!("unknown alphabet '{}'", name))?;
+            }
+            "--seed" => config.seed = value()?.parse()?,
+            "--corpus" => corpus = fs::read_to_string(value()?)?,
+            "--output" => output = Some(value()?),
+            "--key" => key_output = Some(value()?),
+            "--plaintexts" => plaintexts_output = Some(value()?),
+            _ => return Err(USAGE.into()),
+        }
+    }
+
+    let challenge = generate(&corpus, &config)?;
+    let ciphertexts: String = challenge
+        .ciphertexts
+        .iter()
+        .map(|c| encode_hex(c) + "\n")
+        .collect();
+    match output {
+        Some(path) => fs::write(path, ciphertexts)?,
+        None => std::io::stdout().write_all(ciphertexts.as_bytes())?,
+    }
+    if let Some(path) = key_output {
+        fs::write(path, encode_hex(&challenge.key) + "\n")?;
+    }
+    if let Some(path) = plaintexts_output {
+        let mut plaintexts = challenge.plaintexts.join(&b'\n');
+        plaintexts.push(b'\n');
+        fs::write(path, plaintexts)?;
+    }
+
+    let score = challenge.score(&decrypt_key(&challenge.ciphertexts));
+    eprintln!(
+        "seed {}: decrypt_key recovered {:.1}% of {} key bytes, {:.1}% wrong",
+        config.seed,
+        100.0 * score.recovered_fraction(),
+        score.total(),
+        100.0 * score.wrong_fraction()
+    );
+    Ok(())
+}
+
+fn parse_list(arg: &str) -> Result<Vec<usize>, Box<dyn Error>> {
+    Ok(arg
+        .split(',')
+        .map(|n| n.parse())
+        .collect::<Result<_, _>>()?)
+}
+
+// Headless mode: measure every solver on the fixture and generated cases
+fn benchmark(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut messages = vec![5, 10, 20, 50];
+    let mut lengths = vec![50, 100, 200];
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(USAGE);
+        match arg.as_str() {
+            "--messages" => messages = parse_list(value()?)?,
+            "--lengths" => lengths = parse_list(value()?)?,
+            _ => return Err(USAGE.into()),
+        }
+    }
+
+    let mut cases = vec![BenchmarkCase::dan_boneh()];
+    cases.extend(generated_cases(&messages, &lengths));
+    let results = run_benchmark(SOLVERS, &cases);
+    print!("{}", format_results(&results));
+
+    let mut failed = Vec::new();
+    for (solver, _) in SOLVERS {
+        let (recovered, wrong) = average_score(&results, solver);
+        println!(
+            "{}: {:.1}% recovered, {:.1}% wrong on average, {:.3?} and {} column operations in total",
+            solver,
+            100.0 * recovered,
+            100.0 * wrong,
+            total_runtime(&results, solver),
+            total_operations(&results, solver)
+        );
+        if recovered < MIN_RECOVERED || wrong > MAX_WRONG {
+            failed.push(*solver);
+        }
+    }
+    if !failed.is_empty() {
+        return Err(format!("below the accuracy threshold: {}", failed.join(", ")).into());
+    }
+    Ok(())
+}
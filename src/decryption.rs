@@ -9,12 +9,12 @@ fn is_space(c: u8) -> bool {
 }
 
 // XOR two byte arrays
-fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+pub fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
     zip(a, b).map(|(x, y)| x ^ y).collect()
 }
 
 // Return a set with the position of the spaces
-fn get_space_indices(bytes: &[u8]) -> HashSet<usize> {
+pub fn get_space_indices(bytes: &[u8]) -> HashSet<usize> {
     bytes
         .iter()
         .enumerate()
@@ -58,8 +58,168 @@ fn decrypt_key_chunk(encrypted_messages: &Vec<&[u8]>) -> Vec<Option<u8>> {
     key
 }
 
-// Decrypt the key from a list of encrypted messages
+// What the XOR of two plaintext bytes says about them
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum XorClass {
+    // Both bytes are the same character
+    Same,
+    // Most likely a space and a letter
+    SpaceLetter,
+    // Two letters of the same case or two punctuation marks
+    LetterPair,
+    Other,
+}
+
+pub fn classify_xor(byte: u8) -> XorClass {
+    match byte {
+        0x00 => XorClass::Same,
+        _ if is_space(byte) => XorClass::SpaceLetter,
+        0x01..=0x1f => XorClass::LetterPair,
+        _ => XorClass::Other,
+    }
+}
+
+// Weight of a fully trusted message, in percent
+pub const FULL_WEIGHT: u32 = 100;
+
+// Share of the covering weight that must agree on a key byte, in percent.
+// Unanimity is the default; lower thresholds let a few noisy messages be
+// outvoted instead of blocking the column.
+pub const UNANIMOUS: u32 = 100;
+pub const AGREEMENT_THRESHOLDS: [u32; 5] = [UNANIMOUS, 90, 80, 70, 60];
+
+// Per-column tally of the ciphertext bytes, with the total weight of the
+// messages behind each of them, and of how many other messages see each of
+// them as a space
+pub struct ColumnVotes {
+    pub coverage: usize,
+    pub counts: [u32; 256],
+    pub weight: u32,
+    pub weights: [u32; 256],
+    // Messages with a non-zero weight, by byte
+    pub trusted: [u32; 256],
+}
+
+impl ColumnVotes {
+    pub fn new(column: impl IntoIterator<Item = u8>) -> ColumnVotes {
+        let mut votes = ColumnVotes {
+            coverage: 0,
+            counts: [0; 256],
+            weight: 0,
+            weights: [0; 256],
+            trusted: [0; 256],
+        };
+        for byte in column {
+            votes.add(byte, FULL_WEIGHT);
+        }
+        votes
+    }
+
+    pub fn add(&mut self, byte: u8, weight: u32) {
+        self.counts[byte as usize] += 1;
+        self.coverage += 1;
+        self.weights[byte as usize] += weight;
+        self.weight += weight;
+        self.trusted[byte as usize] += (weight > 0) as u32;
+    }
+
+    pub fn remove(&mut self, byte: u8, weight: u32) {
+        self.counts[byte as usize] -= 1;
+        self.coverage -= 1;
+        self.weights[byte as usize] -= weight;
+        self.weight -= weight;
+        self.trusted[byte as usize] -= (weight > 0) as u32;
+    }
+
+    // Number of other messages whose byte XORed with 'byte' looks like a space
+    pub fn votes(&self, byte: u8) -> u32 {
+        SPACE_XORS
+            .iter()
+            .map(|x| self.counts[(byte ^ x) as usize])
+            .sum::<u32>()
+            - 1
+    }
+
+    // Number of messages with a non-zero weight that agree 'byte' is a space,
+    // its own included
+    fn trusted_votes(&self, byte: u8) -> u32 {
+        SPACE_XORS
+            .iter()
+            .map(|x| self.trusted[(byte ^ x) as usize])
+            .sum()
+    }
+
+    // Weight of the messages that agree 'byte' is a space, its own included
+    pub fn support(&self, byte: u8) -> u32 {
+        SPACE_XORS
+            .iter()
+            .map(|x| self.weights[(byte ^ x) as usize])
+            .sum()
+    }
+
+    // Key byte implied by a ciphertext byte that is a space according to at
+    // least 'threshold' percent of the weight covering the column, and to at
+    // least two messages with a non-zero weight, so a message weighed 0 has
+    // no say. The best supported byte wins; ties, common with
+    // few messages, go to the key that turns most of the column into letters
+    // and spaces, then to the most repeated ciphertext byte since spaces are the
+    // most frequent character.
+    pub fn key(&self, threshold: u32) -> Option<u8> {
+        if self.coverage < 2 || self.weight == 0 {
+            return None;
+        }
+        (0..=255u8)
+            .filter(|byte| self.counts[*byte as usize] > 0 && self.trusted_votes(*byte) > 1)
+            .filter(|byte| self.support(*byte) * 100 >= threshold * self.weight)
+            .max_by_key(|byte| {
+                (
+                    self.support(*byte),
+                    self.letters(byte ^ b' '),
+                    self.counts[*byte as usize],
+                )
+            })
+            .map(|byte| byte ^ b' ')
+    }
+
+    // Number of bytes in the column that decrypt to a letter or a space under 'key'
+    fn letters(&self, key: u8) -> u32 {
+        (0..=255u8)
+            .filter(|byte| is_space(byte ^ key ^ b' '))
+            .map(|byte| self.counts[byte as usize])
+            .sum()
+    }
+}
+
+// All the values 'c' for which 'is_space(c)' holds
+const SPACE_XORS: [u8; 53] = {
+    let mut xors = [0; 53];
+    let mut i = 1;
+    while i < 27 {
+        xors[i] = b'A' + i as u8 - 1;
+        xors[i + 26] = b'a' + i as u8 - 1;
+        i += 1;
+    }
+    xors
+};
+
+// Decrypt the key from a list of encrypted messages, one column at a time
 pub fn decrypt_key(encrypted_messages: &[Vec<u8>]) -> Vec<Option<u8>> {
+    let max_len = encrypted_messages
+        .iter()
+        .map(|m| m.len())
+        .max()
+        .unwrap_or(0);
+    (0..max_len)
+        .map(|j| {
+            ColumnVotes::new(encrypted_messages.iter().filter_map(|m| m.get(j).copied()))
+                .key(UNANIMOUS)
+        })
+        .collect()
+}
+
+// Decrypt the key by comparing every pair of messages, one chunk at a time.
+// Kept as a reference for the column-wise solver.
+pub fn decrypt_key_pairwise(encrypted_messages: &[Vec<u8>]) -> Vec<Option<u8>> {
     let mut sorted_ciphertexts: Vec<&[u8]> = encrypted_messages
         .iter()
         .map(|text| text.as_slice())
@@ -84,10 +244,21 @@ pub fn decrypt_key(encrypted_messages: &[Vec<u8>]) -> Vec<Option<u8>> {
     key
 }
 
+// Derive the key bytes implied by a known plaintext placed at 'offset' in 'ciphertext'
+pub fn key_from_plaintext(ciphertext: &[u8], plaintext: &[u8], offset: usize) -> Vec<(usize, u8)> {
+    plaintext
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (offset + i, p))
+        .filter(|(pos, _)| *pos < ciphertext.len())
+        .map(|(pos, p)| (pos, ciphertext[pos] ^ p))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::util::decode_hex;
+    use crate::{challenge::score_key, util::decode_hex};
 
     #[test]
     fn test_dan_boneh_example() {
@@ -168,19 +339,69 @@ mod tests {
             ))
             .unwrap(),
         ];
-        let key: Vec<u8> = decrypt_key(&cipher_texts)
-            .iter()
-            .map(|opt| match opt {
-                Some(val) => *val,
-                None => 0,
-            })
-            .collect();
+        // The last ciphertext is the challenge target, whose plaintext is known
+        let target =
+            b"The secret message is: When using a stream cipher, never use the key more than once";
+        let true_key = xor(&cipher_texts[10], target);
+        let score = score_key(&decrypt_key(&cipher_texts), &true_key);
+        assert!(score.correct >= 60, "{:?}", score);
+        assert!(score.wrong <= 1, "{:?}", score);
+    }
 
-        for byte in key.iter() {
-            match *byte {
-                0 => print!("_"),
-                _ => print!("{:2x}", byte),
-            }
-        }
+    #[test]
+    fn test_classify_xor() {
+        let x = xor(b"to Be", b"t  ia");
+        let classes: Vec<XorClass> = x.iter().map(|b| classify_xor(*b)).collect();
+        assert_eq!(
+            classes,
+            vec![
+                XorClass::Same,
+                XorClass::SpaceLetter,
+                XorClass::Same,
+                XorClass::Other,
+                XorClass::LetterPair
+            ]
+        );
+        assert_eq!(get_space_indices(&x), [0, 1, 2].into_iter().collect());
+    }
+
+    #[test]
+    fn test_weighted_votes() {
+        let key = 0x5a;
+        // Four messages with a space in this column and one noisy message
+        let column: Vec<u8> = b"    ~".iter().map(|c| c ^ key).collect();
+        let mut votes = ColumnVotes::new(column.iter().copied());
+        assert_eq!(votes.key(UNANIMOUS), None);
+        assert_eq!(votes.key(80), Some(key));
+
+        // Trusting the noisy message less lets it be outvoted at 90%
+        votes.remove(column[4], FULL_WEIGHT);
+        votes.add(column[4], 20);
+        assert_eq!(votes.key(UNANIMOUS), None);
+        assert_eq!(votes.key(90), Some(key));
+
+        // A message trusted on its own is not enough
+        let mut votes = ColumnVotes::new(None);
+        votes.add(column[0], FULL_WEIGHT);
+        votes.add(column[4], 10);
+        assert_eq!(votes.key(60), None);
+
+        // Nor is one backed only by a message weighed 0
+        votes.remove(column[4], 10);
+        votes.add(column[1], 0);
+        assert_eq!(votes.key(UNANIMOUS), None);
+        votes.remove(column[1], 0);
+        votes.add(column[1], 10);
+        assert_eq!(votes.key(UNANIMOUS), Some(key));
+    }
+
+    #[test]
+    fn test_key_from_plaintext() {
+        let key = [0x13, 0x37, 0xc0, 0xde, 0x42];
+        let ciphertext = xor(b"hello", &key);
+        assert_eq!(
+            key_from_plaintext(&ciphertext, b"llo world", 2),
+            vec![(2, 0xc0), (3, 0xde), (4, 0x42)]
+        );
     }
 }
@@ -0,0 +1,293 @@
+use crate::plaintext::KeyOrigin;
+
+// Formats the key, the plaintexts or the whole analysis can be written in
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExportFormat {
+    KeyHex,
+    KeyBinary,
+    KeyRust,
+    KeyPython,
+    Plaintexts,
+    Markdown,
+    Html,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 7] = [
+        ExportFormat::KeyHex,
+        ExportFormat::KeyBinary,
+        ExportFormat::KeyRust,
+        ExportFormat::KeyPython,
+        ExportFormat::Plaintexts,
+        ExportFormat::Markdown,
+        ExportFormat::Html,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ExportFormat::KeyHex => "key-hex",
+            ExportFormat::KeyBinary => "key-bin",
+            ExportFormat::KeyRust => "key-rust",
+            ExportFormat::KeyPython => "key-python",
+            ExportFormat::Plaintexts => "plaintexts",
+            ExportFormat::Markdown => "markdown",
+            ExportFormat::Html => "html",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::KeyHex => "hex",
+            ExportFormat::KeyBinary => "bin",
+            ExportFormat::KeyRust => "rs",
+            ExportFormat::KeyPython => "py",
+            ExportFormat::Plaintexts => "txt",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+        }
+    }
+
+    // Whether the plaintexts, and so the unknown marker, are written
+    pub fn has_plaintexts(self) -> bool {
+        matches!(
+            self,
+            ExportFormat::Plaintexts | ExportFormat::Markdown | ExportFormat::Html
+        )
+    }
+
+    pub fn from_name(name: &str) -> Option<ExportFormat> {
+        ExportFormat::ALL.into_iter().find(|f| f.name() == name)
+    }
+
+    pub fn next(self) -> ExportFormat {
+        let i = ExportFormat::ALL.iter().position(|f| *f == self).unwrap();
+        ExportFormat::ALL[(i + 1) % ExportFormat::ALL.len()]
+    }
+
+    pub fn previous(self) -> ExportFormat {
+        let i = ExportFormat::ALL.iter().position(|f| *f == self).unwrap();
+        ExportFormat::ALL[(i + ExportFormat::ALL.len() - 1) % ExportFormat::ALL.len()]
+    }
+}
+
+// Markers for unknown plaintext bytes the export can cycle through
+pub const UNKNOWN_MARKERS: [char; 5] = ['_', '?', '.', '*', ' '];
+
+// Everything an export needs to know about the current analysis
+pub struct Analysis<'a> {
+    pub encrypted_messages: &'a [Vec<u8>],
+    pub key: &'a [Option<u8>],
+    pub key_origin: &'a [KeyOrigin],
+    pub unknown_marker: char,
+}
+
+impl<'a> Analysis<'a> {
+    // A binary key has no way to mark unknown bytes, so it must be complete
+    pub fn export(&self, format: ExportFormat) -> Result<Vec<u8>, String> {
+        Ok(match format {
+            ExportFormat::KeyHex => self.key_hex().into_bytes(),
+            ExportFormat::KeyBinary => {
+                let unknown = self.key.iter().filter(|b| b.is_none()).count();
+                if unknown > 0 {
+                    return Err(format!(
+                        "{} of {} key bytes are unknown, a binary key must be complete",
+                        unknown,
+                        self.key.len()
+                    ));
+                }
+                self.key.iter().flatten().copied().collect()
+            }
+            ExportFormat::KeyRust => self.key_rust().into_bytes(),
+            ExportFormat::KeyPython => self.key_python().into_bytes(),
+            ExportFormat::Plaintexts => (self.plaintexts().join("\n") + "\n").into_bytes(),
+            ExportFormat::Markdown => self.markdown().into_bytes(),
+            ExportFormat::Html => self.html().into_bytes(),
+        })
+    }
+
+    fn key_hex(&self) -> String {
+        let mut hex: String = self
+            .key
+            .iter()
+            .map(|opt| match opt {
+                Some(byte) => format!("{:02x}", byte),
+                None => "__".to_string(),
+            })
+            .collect();
+        hex.push('\n');
+        hex
+    }
+
+    // Comma separated array items, twelve per line
+    fn array_items(&self, known: &dyn Fn(u8) -> String, unknown: &str) -> String {
+        self.key
+            .chunks(12)
+            .map(|chunk| {
+                let items: Vec<String> = chunk
+                    .iter()
+                    .map(|opt| match opt {
+                        Some(byte) => known(*byte),
+                        None => unknown.to_string(),
+                    })
+                    .collect();
+                format!("    {},\n", items.join(", "))
+            })
+            .collect()
+    }
+
+    fn key_rust(&self) -> String {
+        if self.key.iter().all(|b| b.is_some()) {
+            format!(
+                "pub const KEY: [u8; {}] = [\n{}];\n",
+                self.key.len(),
+                self.array_items(&|b| format!("0x{:02x}", b), "")
+            )
+        } else {
+            format!(
+                "pub const KEY: [Option<u8>; {}] = [\n{}];\n",
+                self.key.len(),
+                self.array_items(&|b| format!("Some(0x{:02x})", b), "None")
+            )
+        }
+    }
+
+    fn key_python(&self) -> String {
+        let items = self.array_items(&|b| format!("0x{:02x}", b), "None");
+        if self.key.iter().all(|b| b.is_some()) {
+            format!("KEY = bytes([\n{}])\n", items)
+        } else {
+            format!("KEY = [\n{}]\n", items)
+        }
+    }
+
+    // Decrypted messages, with the unknown marker for missing key bytes and
+    // '?' for bytes that are not printable
+    pub fn plaintexts(&self) -> Vec<String> {
+        self.encrypted_messages
+            .iter()
+            .map(|msg| {
+                msg.iter()
+                    .zip(self.key)
+                    .map(|(c, k)| match k {
+                        Some(k) if (c ^ k) == b' ' || (c ^ k).is_ascii_graphic() => (c ^ k) as char,
+                        Some(_) => '?',
+                        None => self.unknown_marker,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn coverage(&self, column: usize) -> usize {
+        self.encrypted_messages
+            .iter()
+            .filter(|msg| msg.len() > column)
+            .count()
+    }
+
+    fn markdown(&self) -> String {
+        let mut report = String::from("# Many-time pad analysis\n\n## Key\n\n```\n");
+        report.push_str(&self.key_hex());
+        report.push_str("```\n\n## Plaintexts\n\n```\n");
+        for (i, plaintext) in self.plaintexts().iter().enumerate() {
+            report.push_str(&format!("{}: {}\n", i, plaintext));
+        }
+        report.push_str("```\n\n## Key provenance\n\n");
+        report.push_str("| Column | Key byte | Origin | Coverage |\n");
+        report.push_str("|-------:|:--------:|:-------|---------:|\n");
+        for (column, (byte, origin)) in self.key.iter().zip(self.key_origin).enumerate() {
+            let byte = match byte {
+                Some(byte) => format!("`{:02x}`", byte),
+                None => "".to_string(),
+            };
+            report.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                column,
+                byte,
+                origin.name(),
+                self.coverage(column)
+            ));
+        }
+        report
+    }
+
+    fn html(&self) -> String {
+        let mut report = String::from(concat!(
+            "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\">",
+            "<title>Many-time pad analysis</title></head>\n<body>\n",
+            "<h1>Many-time pad analysis</h1>\n<h2>Key</h2>\n<pre>"
+        ));
+        report.push_str(self.key_hex().trim_end());
+        report.push_str("</pre>\n<h2>Plaintexts</h2>\n<pre>\n");
+        for (i, plaintext) in self.plaintexts().iter().enumerate() {
+            report.push_str(&format!("{}: {}\n", i, escape_html(plaintext)));
+        }
+        report.push_str("</pre>\n<h2>Key provenance</h2>\n<table>\n");
+        report.push_str(
+            "<tr><th>Column</th><th>Key byte</th><th>Origin</th><th>Coverage</th></tr>\n",
+        );
+        for (column, (byte, origin)) in self.key.iter().zip(self.key_origin).enumerate() {
+            let byte = match byte {
+                Some(byte) => format!("{:02x}", byte),
+                None => "".to_string(),
+            };
+            report.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                column,
+                byte,
+                origin.name(),
+                self.coverage(column)
+            ));
+        }
+        report.push_str("</table>\n</body>\n</html>\n");
+        report
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_key() {
+        let encrypted_messages = vec![vec![b'h' ^ 0x01, b'i' ^ 0x02, 0x7f]];
+        let key = [Some(0x01), Some(0x02), None];
+        let key_origin = [KeyOrigin::Solver, KeyOrigin::Manual, KeyOrigin::Unknown];
+        let analysis = Analysis {
+            encrypted_messages: &encrypted_messages,
+            key: &key,
+            key_origin: &key_origin,
+            unknown_marker: '#',
+        };
+        assert_eq!(analysis.export(ExportFormat::KeyHex).unwrap(), b"0102__\n");
+        assert!(analysis.export(ExportFormat::KeyBinary).is_err());
+        assert_eq!(analysis.export(ExportFormat::Plaintexts).unwrap(), b"hi#\n");
+        assert_eq!(
+            String::from_utf8(analysis.export(ExportFormat::KeyRust).unwrap()).unwrap(),
+            "pub const KEY: [Option<u8>; 3] = [\n    Some(0x01), Some(0x02), None,\n];\n"
+        );
+        assert_eq!(
+            String::from_utf8(analysis.export(ExportFormat::KeyPython).unwrap()).unwrap(),
+            "KEY = [\n    0x01, 0x02, None,\n]\n"
+        );
+        let markdown = String::from_utf8(analysis.export(ExportFormat::Markdown).unwrap()).unwrap();
+        assert!(markdown.contains("| 1 | `02` | manual | 1 |"));
+
+        let key = [Some(0x01), Some(0x02), Some(0x03)];
+        let analysis = Analysis {
+            key: &key,
+            ..analysis
+        };
+        assert_eq!(
+            analysis.export(ExportFormat::KeyBinary).unwrap(),
+            vec![1, 2, 3]
+        );
+    }
+}
@@ -0,0 +1,48 @@
+use rand::RngCore;
+
+use crate::decryption::xor;
+
+// Generate a random one-time pad of 'len' bytes
+pub fn random_key(len: usize) -> Vec<u8> {
+    let mut key = vec![0; len];
+    rand::thread_rng().fill_bytes(&mut key);
+    key
+}
+
+// Encrypt 'plaintext' with 'key', growing the key with random bytes if it is too short
+pub fn encrypt(plaintext: &[u8], key: &mut Vec<u8>) -> Vec<u8> {
+    if key.len() < plaintext.len() {
+        let missing = plaintext.len() - key.len();
+        key.extend(random_key(missing));
+    }
+    xor(plaintext, key)
+}
+
+// Build the ciphertext of 'plaintext' from a recovered key. Bytes whose key
+// byte is still unknown cannot be forged and are left as None.
+pub fn forge(plaintext: &[u8], key: &[Option<u8>]) -> Vec<Option<u8>> {
+    plaintext
+        .iter()
+        .enumerate()
+        .map(|(i, p)| key.get(i).copied().flatten().map(|k| p ^ k))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_and_forge() {
+        let mut key = vec![0x01, 0x02];
+        let ciphertext = encrypt(b"abcd", &mut key);
+        assert_eq!(key.len(), 4);
+        assert_eq!(&ciphertext[..2], &[b'a' ^ 0x01, b'b' ^ 0x02]);
+
+        let recovered = [Some(key[0]), None, Some(key[2])];
+        assert_eq!(
+            forge(b"wxyz", &recovered),
+            vec![Some(b'w' ^ key[0]), None, Some(b'y' ^ key[2]), None]
+        );
+    }
+}
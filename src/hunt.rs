@@ -0,0 +1,155 @@
+use regex_syntax::hir::literal::{ExtractKind, Extractor};
+
+use crate::{
+    decryption::key_from_plaintext, language::LanguageModel, plaintext::Alphabet, search::find_fits,
+};
+
+// A placement of a flag prefix in a message and the key bytes it would add
+pub struct Crib {
+    pub message: usize,
+    pub offset: usize,
+    // What is placed, for display
+    pub label: String,
+    pub key: Vec<(usize, u8)>,
+    // Average bits per character the new key bytes give the other messages
+    // under the language model; lower is more plausible
+    pub score: f64,
+    // Known key bytes the placement agrees with; more is more plausible.
+    // Only file signatures are ranked by it.
+    pub confirmed: usize,
+}
+
+// Literal prefixes every flag must start with. A pattern that is not a valid
+// regex, such as "flag{", is taken as a literal prefix.
+pub fn flag_prefixes(pattern: &str) -> Result<Vec<Vec<u8>>, String> {
+    let hir = match regex_syntax::parse(pattern) {
+        Ok(hir) => hir,
+        Err(_) if !pattern.is_empty() => return Ok(vec![pattern.as_bytes().to_vec()]),
+        Err(err) => return Err(err.to_string()),
+    };
+    let prefixes: Vec<Vec<u8>> = Extractor::new()
+        .kind(ExtractKind::Prefix)
+        .extract(&hir)
+        .literals()
+        .unwrap_or_default()
+        .iter()
+        .filter(|literal| !literal.as_bytes().is_empty())
+        .map(|literal| literal.as_bytes().to_vec())
+        .collect();
+    match prefixes.is_empty() {
+        true => Err("the pattern has no literal prefix".to_string()),
+        false => Ok(prefixes),
+    }
+}
+
+// Bits per character of the other messages over the columns a crib fills in
+fn score(
+    model: &LanguageModel,
+    encrypted_messages: &[Vec<u8>],
+    enabled: &[bool],
+    key: &[Option<u8>],
+    crib: &Crib,
+) -> f64 {
+    let mut key = key.to_vec();
+    for (pos, byte) in &crib.key {
+        key[*pos] = Some(*byte);
+    }
+    let (mut bits, mut count) = (0.0, 0);
+    for (i, msg) in encrypted_messages.iter().enumerate() {
+        if i == crib.message || !enabled[i] {
+            continue;
+        }
+        for (pos, byte) in crib.key.iter().filter(|(pos, _)| *pos < msg.len()) {
+            let context: Vec<Option<u8>> = (pos.saturating_sub(2)..*pos)
+                .map(|j| key[j].map(|k| msg[j] ^ k))
+                .collect();
+            bits -= model.probability(&context, msg[*pos] ^ byte).log2();
+            count += 1;
+        }
+    }
+    match count {
+        0 => f64::INFINITY,
+        _ => bits / count as f64,
+    }
+}
+
+// Every placement of a prefix consistent with the key and the alphabet that
+// adds key bytes, most plausible first
+pub fn hunt(
+    prefixes: &[Vec<u8>],
+    encrypted_messages: &[Vec<u8>],
+    enabled: &[bool],
+    key: &[Option<u8>],
+    alphabet: Alphabet,
+    model: &LanguageModel,
+) -> Vec<Crib> {
+    let mut cribs = Vec::new();
+    for prefix in prefixes {
+        for fit in find_fits(prefix, encrypted_messages, enabled, key, alphabet) {
+            let new_key: Vec<(usize, u8)> =
+                key_from_plaintext(&encrypted_messages[fit.message], prefix, fit.start)
+                    .into_iter()
+                    .filter(|(pos, _)| key[*pos].is_none())
+                    .collect();
+            if new_key.is_empty() {
+                continue;
+            }
+            let mut crib = Crib {
+                message: fit.message,
+                offset: fit.start,
+                label: String::from_utf8_lossy(prefix).into_owned(),
+                key: new_key,
+                score: 0.0,
+                confirmed: 0,
+            };
+            crib.score = score(model, encrypted_messages, enabled, key, &crib);
+            cribs.push(crib);
+        }
+    }
+    cribs.sort_by(|a, b| a.score.total_cmp(&b.score));
+    cribs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{challenge::SAMPLE_CORPUS, decryption::xor};
+
+    #[test]
+    fn test_hunt() {
+        assert_eq!(
+            flag_prefixes("(flag|CTF)\\{[a-z_]+\\}").unwrap(),
+            vec![b"flag{".to_vec(), b"CTF{".to_vec()]
+        );
+        assert_eq!(flag_prefixes("flag{").unwrap(), vec![b"flag{".to_vec()]);
+        assert!(flag_prefixes(".*").is_err());
+
+        let plaintexts: [&[u8]; 4] = [
+            b"the answer is flag{xor}",
+            b"secrets leak when pads repeat",
+            b"never reuse the same key stream",
+            b"stream ciphers need nonces too",
+        ];
+        let true_key: Vec<u8> = (0..31).map(|i| (i * 37 + 11) as u8).collect();
+        let messages: Vec<Vec<u8>> = plaintexts.iter().map(|p| xor(p, &true_key)).collect();
+        let mut key: Vec<Option<u8>> = true_key.iter().copied().map(Some).collect();
+        for byte in &mut key[14..19] {
+            *byte = None;
+        }
+        let model = LanguageModel::new(SAMPLE_CORPUS);
+        let cribs = hunt(
+            &flag_prefixes("flag\\{").unwrap(),
+            &messages,
+            &[true; 4],
+            &key,
+            Alphabet::Printable,
+            &model,
+        );
+        assert_eq!((cribs[0].message, cribs[0].offset), (0, 14));
+        assert_eq!(cribs[0].key.len(), 5);
+        assert!(cribs[0]
+            .key
+            .iter()
+            .all(|(pos, byte)| true_key[*pos] == *byte));
+    }
+}
@@ -0,0 +1,827 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::app_state::{InputMode, Tab};
+
+// What a key press does; `run_app` interprets it for the current tab and mode
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    Quit,
+    NextTab,
+    PreviousTab,
+    Help,
+    Notifications,
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    WordLeft,
+    WordRight,
+    Confirm,
+    Cancel,
+    Backspace,
+    Remove,
+    // Any character that no other binding claims
+    Type,
+    Edit,
+    EditKey,
+    EditForgery,
+    GenerateKey,
+    ToggleMessage,
+    NextAlphabet,
+    TogglePropagation,
+    TogglePreview,
+    Overwrite,
+    Force,
+    Undo,
+    KnownPlaintext,
+    Search,
+    NextMatch,
+    PreviousMatch,
+    Hunt,
+    Signatures,
+    ToggleBinary,
+    WriteFiles,
+    NextNonceLength,
+    IsolateNonce,
+    IncreaseWeight,
+    DecreaseWeight,
+    WeighByReadability,
+    NextThreshold,
+    Verify,
+    Patterns,
+    Export,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+const fn key(code: KeyCode) -> Key {
+    Key {
+        code,
+        modifiers: KeyModifiers::NONE,
+    }
+}
+
+const fn ctrl(code: KeyCode) -> Key {
+    Key {
+        code,
+        modifiers: KeyModifiers::CONTROL,
+    }
+}
+
+impl Key {
+    // Shift is part of the character for letters and symbols, so it is ignored
+    fn matches(&self, event: KeyEvent) -> bool {
+        self.code == event.code && self.modifiers == event.modifiers - KeyModifiers::SHIFT
+    }
+}
+
+pub struct Binding {
+    // An empty list stands for any character
+    pub keys: &'static [Key],
+    pub action: Action,
+    pub help: &'static str,
+}
+
+const fn bind(keys: &'static [Key], action: Action, help: &'static str) -> Binding {
+    Binding { keys, action, help }
+}
+
+// F1 is never typed text, so help opens from every mode
+const GLOBAL: &[Binding] = &[
+    bind(
+        &[key(KeyCode::F(1))],
+        Action::Help,
+        "show the keys of this mode",
+    ),
+    bind(&[key(KeyCode::Tab)], Action::NextTab, "go to the next tab"),
+];
+
+const GLOBAL_NORMAL: &[Binding] = &[
+    bind(&[key(KeyCode::Char('?'))], Action::Help, "show this help"),
+    bind(
+        &[key(KeyCode::Char('m'))],
+        Action::Notifications,
+        "show recent notifications",
+    ),
+    bind(&[key(KeyCode::Char('q'))], Action::Quit, "quit"),
+];
+
+const ENCRYPTED_NORMAL: &[Binding] = &[
+    bind(&[key(KeyCode::Char('e'))], Action::Edit, "add a ciphertext"),
+    bind(
+        &[key(KeyCode::Up)],
+        Action::Up,
+        "select the previous message",
+    ),
+    bind(
+        &[key(KeyCode::Down)],
+        Action::Down,
+        "select the next message",
+    ),
+    bind(
+        &[key(KeyCode::Char('d'))],
+        Action::ToggleMessage,
+        "disable or enable the selected message",
+    ),
+    bind(
+        &[key(KeyCode::Delete)],
+        Action::Remove,
+        "remove the selected message",
+    ),
+    bind(
+        &[key(KeyCode::Char('n'))],
+        Action::NextNonceLength,
+        "change the nonce prefix stripped from new ciphertexts",
+    ),
+    bind(
+        &[key(KeyCode::Char('g'))],
+        Action::IsolateNonce,
+        "solve only the messages sharing the selected nonce",
+    ),
+    bind(
+        &[key(KeyCode::Char('+'))],
+        Action::IncreaseWeight,
+        "trust the selected message more",
+    ),
+    bind(
+        &[key(KeyCode::Char('-'))],
+        Action::DecreaseWeight,
+        "trust the selected message less",
+    ),
+    bind(
+        &[key(KeyCode::Char('w'))],
+        Action::WeighByReadability,
+        "weigh every message by how readable it decrypts",
+    ),
+    bind(
+        &[key(KeyCode::Right)],
+        Action::NextTab,
+        "go to the next tab",
+    ),
+    bind(
+        &[key(KeyCode::Left)],
+        Action::PreviousTab,
+        "go to the previous tab",
+    ),
+];
+
+const ENCRYPTED_EDITING: &[Binding] = &[
+    bind(&[], Action::Type, "type the hex ciphertext"),
+    bind(&[key(KeyCode::Left)], Action::Left, "move the cursor left"),
+    bind(
+        &[key(KeyCode::Right)],
+        Action::Right,
+        "move the cursor right",
+    ),
+    bind(
+        &[ctrl(KeyCode::Left)],
+        Action::WordLeft,
+        "move to the previous word",
+    ),
+    bind(
+        &[ctrl(KeyCode::Right)],
+        Action::WordRight,
+        "move to the next word",
+    ),
+    bind(&[key(KeyCode::Home)], Action::Home, "move to the start"),
+    bind(&[key(KeyCode::End)], Action::End, "move to the end"),
+    bind(
+        &[key(KeyCode::Backspace)],
+        Action::Backspace,
+        "delete the character before the cursor",
+    ),
+    bind(
+        &[key(KeyCode::Delete)],
+        Action::Remove,
+        "delete the character under the cursor",
+    ),
+    bind(
+        &[key(KeyCode::Enter)],
+        Action::Confirm,
+        "record the message",
+    ),
+    bind(&[key(KeyCode::Esc)], Action::Cancel, "stop editing"),
+];
+
+const DECRYPTION_NORMAL: &[Binding] = &[
+    bind(
+        &[key(KeyCode::Char('e'))],
+        Action::Edit,
+        "edit the plaintexts",
+    ),
+    bind(
+        &[key(KeyCode::Char('u'))],
+        Action::Undo,
+        "undo the last key edit",
+    ),
+    bind(
+        &[key(KeyCode::Char('a'))],
+        Action::NextAlphabet,
+        "change the alphabet",
+    ),
+    bind(
+        &[key(KeyCode::Char('p'))],
+        Action::TogglePropagation,
+        "toggle propagation",
+    ),
+    bind(
+        &[key(KeyCode::Char('k'))],
+        Action::KnownPlaintext,
+        "enter a known plaintext",
+    ),
+    bind(
+        &[key(KeyCode::Char('/'))],
+        Action::Search,
+        "search the plaintexts",
+    ),
+    bind(
+        &[key(KeyCode::Char('n'))],
+        Action::NextMatch,
+        "go to the next match",
+    ),
+    bind(
+        &[key(KeyCode::Char('N'))],
+        Action::PreviousMatch,
+        "go to the previous match",
+    ),
+    bind(
+        &[key(KeyCode::Char('f'))],
+        Action::Hunt,
+        "hunt for the flag",
+    ),
+    bind(
+        &[key(KeyCode::Char('s'))],
+        Action::Signatures,
+        "place known file headers",
+    ),
+    bind(
+        &[key(KeyCode::Char('b'))],
+        Action::ToggleBinary,
+        "toggle showing the plaintexts as hex",
+    ),
+    bind(
+        &[key(KeyCode::Char('w'))],
+        Action::WriteFiles,
+        "write the recovered plaintexts as files",
+    ),
+    bind(
+        &[key(KeyCode::Char('v'))],
+        Action::Verify,
+        "check the key against a stream cipher keystream",
+    ),
+    bind(
+        &[key(KeyCode::Char('r'))],
+        Action::Patterns,
+        "look for repeats and PRNG structure in the key",
+    ),
+    bind(
+        &[key(KeyCode::Char('t'))],
+        Action::NextThreshold,
+        "change how many messages must agree on a key byte",
+    ),
+    bind(
+        &[key(KeyCode::Char('x'))],
+        Action::Export,
+        "export the key or plaintexts",
+    ),
+];
+
+const DECRYPTION_EDITING: &[Binding] = &[
+    bind(
+        &[],
+        Action::Type,
+        "set the plaintext character under the cursor",
+    ),
+    bind(
+        &[key(KeyCode::Up)],
+        Action::Up,
+        "move to the previous message",
+    ),
+    bind(
+        &[key(KeyCode::Down)],
+        Action::Down,
+        "move to the next message",
+    ),
+    bind(&[key(KeyCode::Left)], Action::Left, "move the cursor left"),
+    bind(
+        &[key(KeyCode::Right)],
+        Action::Right,
+        "move the cursor right",
+    ),
+    bind(
+        &[key(KeyCode::Backspace)],
+        Action::Backspace,
+        "forget the key byte before the cursor",
+    ),
+    bind(
+        &[ctrl(KeyCode::Char('p'))],
+        Action::TogglePreview,
+        "toggle previewing guesses before they are set",
+    ),
+    bind(
+        &[ctrl(KeyCode::Char('o'))],
+        Action::Overwrite,
+        "overwrite a span from the cursor",
+    ),
+    bind(
+        &[ctrl(KeyCode::Char('z'))],
+        Action::Undo,
+        "undo the last key edit",
+    ),
+    bind(
+        &[key(KeyCode::Enter)],
+        Action::Confirm,
+        "set the previewed guess",
+    ),
+    bind(
+        &[key(KeyCode::Esc)],
+        Action::Cancel,
+        "discard the previewed guess, or stop editing",
+    ),
+];
+
+const OVERWRITE: &[Binding] = &[
+    bind(&[], Action::Type, "type the replacement"),
+    bind(&[key(KeyCode::Left)], Action::Left, "shrink the selection"),
+    bind(
+        &[key(KeyCode::Right)],
+        Action::Right,
+        "extend the selection",
+    ),
+    bind(
+        &[key(KeyCode::Backspace)],
+        Action::Backspace,
+        "delete the last character",
+    ),
+    bind(
+        &[key(KeyCode::Enter)],
+        Action::Confirm,
+        "set the key bytes of the replacement",
+    ),
+    bind(
+        &[ctrl(KeyCode::Char('f'))],
+        Action::Force,
+        "set them even if other messages leave the alphabet",
+    ),
+    bind(&[key(KeyCode::Esc)], Action::Cancel, "cancel"),
+];
+
+const KNOWN_PLAINTEXT: &[Binding] = &[
+    bind(
+        &[],
+        Action::Type,
+        "type the plaintext, prefixed by @<offset> to skip bytes",
+    ),
+    bind(&[key(KeyCode::Up)], Action::Up, "pick the previous message"),
+    bind(&[key(KeyCode::Down)], Action::Down, "pick the next message"),
+    bind(
+        &[key(KeyCode::Backspace)],
+        Action::Backspace,
+        "delete the last character",
+    ),
+    bind(
+        &[key(KeyCode::Enter)],
+        Action::Confirm,
+        "apply the plaintext to the key",
+    ),
+    bind(&[key(KeyCode::Esc)], Action::Cancel, "cancel"),
+];
+
+const EXPORT: &[Binding] = &[
+    bind(&[], Action::Type, "type the output path"),
+    bind(
+        &[key(KeyCode::Left)],
+        Action::Left,
+        "pick the previous format",
+    ),
+    bind(
+        &[key(KeyCode::Right)],
+        Action::Right,
+        "pick the next format",
+    ),
+    bind(
+        &[key(KeyCode::Up)],
+        Action::Up,
+        "pick the previous marker for unknown bytes",
+    ),
+    bind(
+        &[key(KeyCode::Down)],
+        Action::Down,
+        "pick the next marker for unknown bytes",
+    ),
+    bind(
+        &[key(KeyCode::Backspace)],
+        Action::Backspace,
+        "delete the last character",
+    ),
+    bind(&[key(KeyCode::Enter)], Action::Confirm, "write the file"),
+    bind(&[key(KeyCode::Esc)], Action::Cancel, "cancel"),
+];
+
+const VERIFY: &[Binding] = &[
+    bind(&[], Action::Type, "type the test key in hex"),
+    bind(
+        &[key(KeyCode::Left), key(KeyCode::Right)],
+        Action::Right,
+        "switch between AES-CTR, AES-GCM and ChaCha20",
+    ),
+    bind(
+        &[key(KeyCode::Backspace)],
+        Action::Backspace,
+        "delete the last character",
+    ),
+    bind(
+        &[key(KeyCode::Enter)],
+        Action::Confirm,
+        "compare the keystreams",
+    ),
+    bind(&[key(KeyCode::Esc)], Action::Cancel, "cancel"),
+];
+
+const SEARCH: &[Binding] = &[
+    bind(
+        &[],
+        Action::Type,
+        "type the pattern, unknown bytes match anything",
+    ),
+    bind(
+        &[key(KeyCode::Left), key(KeyCode::Right)],
+        Action::Right,
+        "switch between regex and fit",
+    ),
+    bind(
+        &[key(KeyCode::Backspace)],
+        Action::Backspace,
+        "delete the last character",
+    ),
+    bind(
+        &[key(KeyCode::Enter)],
+        Action::Confirm,
+        "jump to the first match",
+    ),
+    bind(&[key(KeyCode::Esc)], Action::Cancel, "clear the search"),
+];
+
+const HUNT: &[Binding] = &[
+    bind(&[], Action::Type, "type the flag regex or prefix"),
+    bind(
+        &[key(KeyCode::Up)],
+        Action::Up,
+        "pick the previous candidate",
+    ),
+    bind(
+        &[key(KeyCode::Down)],
+        Action::Down,
+        "pick the next candidate",
+    ),
+    bind(
+        &[key(KeyCode::Backspace)],
+        Action::Backspace,
+        "delete the last character",
+    ),
+    bind(
+        &[key(KeyCode::Enter)],
+        Action::Confirm,
+        "accept the candidate",
+    ),
+    bind(&[key(KeyCode::Esc)], Action::Cancel, "cancel"),
+];
+
+const SIGNATURES: &[Binding] = &[
+    bind(
+        &[key(KeyCode::Up)],
+        Action::Up,
+        "pick the previous candidate",
+    ),
+    bind(
+        &[key(KeyCode::Down)],
+        Action::Down,
+        "pick the next candidate",
+    ),
+    bind(
+        &[key(KeyCode::Enter)],
+        Action::Confirm,
+        "accept the candidate",
+    ),
+    bind(&[key(KeyCode::Esc)], Action::Cancel, "cancel"),
+];
+
+const PATTERNS: &[Binding] = &[
+    bind(&[key(KeyCode::Up)], Action::Up, "pick the previous pattern"),
+    bind(&[key(KeyCode::Down)], Action::Down, "pick the next pattern"),
+    bind(
+        &[key(KeyCode::Enter)],
+        Action::Confirm,
+        "extend the key with the pattern",
+    ),
+    bind(&[key(KeyCode::Esc)], Action::Cancel, "cancel"),
+];
+
+const XOR: &[Binding] = &[
+    bind(
+        &[key(KeyCode::Up)],
+        Action::Up,
+        "pick the previous first message",
+    ),
+    bind(
+        &[key(KeyCode::Down)],
+        Action::Down,
+        "pick the next first message",
+    ),
+    bind(
+        &[key(KeyCode::PageUp)],
+        Action::PageUp,
+        "pick the previous second message",
+    ),
+    bind(
+        &[key(KeyCode::PageDown)],
+        Action::PageDown,
+        "pick the next second message",
+    ),
+    bind(
+        &[key(KeyCode::Left)],
+        Action::Left,
+        "move to the previous column",
+    ),
+    bind(
+        &[key(KeyCode::Right)],
+        Action::Right,
+        "move to the next column",
+    ),
+    bind(
+        &[key(KeyCode::Enter)],
+        Action::Confirm,
+        "edit the column in the Decryption tab",
+    ),
+];
+
+const STATISTICS: &[Binding] = &[
+    bind(
+        &[key(KeyCode::Left)],
+        Action::Left,
+        "move to the previous column",
+    ),
+    bind(
+        &[key(KeyCode::Right)],
+        Action::Right,
+        "move to the next column",
+    ),
+    bind(
+        &[key(KeyCode::Home)],
+        Action::Home,
+        "go to the first column",
+    ),
+    bind(&[key(KeyCode::End)], Action::End, "go to the last column"),
+];
+
+const FORGE_NORMAL: &[Binding] = &[
+    bind(
+        &[key(KeyCode::Char('e'))],
+        Action::Edit,
+        "encrypt a plaintext",
+    ),
+    bind(&[key(KeyCode::Char('k'))], Action::EditKey, "type a key"),
+    bind(
+        &[key(KeyCode::Char('g'))],
+        Action::GenerateKey,
+        "generate a random key",
+    ),
+    bind(
+        &[key(KeyCode::Char('f'))],
+        Action::EditForgery,
+        "forge a ciphertext",
+    ),
+];
+
+const FORGE_EDITING: &[Binding] = &[
+    bind(&[], Action::Type, "type into the field"),
+    bind(
+        &[key(KeyCode::Backspace)],
+        Action::Backspace,
+        "delete the last character",
+    ),
+    bind(
+        &[key(KeyCode::Enter)],
+        Action::Confirm,
+        "encrypt, set the key or forge",
+    ),
+    bind(&[key(KeyCode::Esc)], Action::Cancel, "stop editing"),
+];
+
+const BULK_PASTE: &[Binding] = &[
+    bind(
+        &[key(KeyCode::Enter)],
+        Action::Confirm,
+        "add the accepted lines as ciphertexts",
+    ),
+    bind(&[key(KeyCode::Esc)], Action::Cancel, "discard the paste"),
+];
+
+// Bindings specific to a tab and mode
+fn local_bindings(tab: &Tab, mode: &InputMode) -> &'static [Binding] {
+    match (tab, mode) {
+        (Tab::Encrypted, InputMode::Normal) => ENCRYPTED_NORMAL,
+        (Tab::Encrypted, InputMode::Editing) => ENCRYPTED_EDITING,
+        (Tab::Encrypted, InputMode::BulkPaste) => BULK_PASTE,
+        (Tab::Decryption, InputMode::Normal) => DECRYPTION_NORMAL,
+        (Tab::Decryption, InputMode::Editing) => DECRYPTION_EDITING,
+        (Tab::Decryption, InputMode::KnownPlaintext) => KNOWN_PLAINTEXT,
+        (Tab::Decryption, InputMode::Export) => EXPORT,
+        (Tab::Decryption, InputMode::Search) => SEARCH,
+        (Tab::Decryption, InputMode::Hunt) => HUNT,
+        (Tab::Decryption, InputMode::Signatures) => SIGNATURES,
+        (Tab::Decryption, InputMode::Verify) => VERIFY,
+        (Tab::Decryption, InputMode::Patterns) => PATTERNS,
+        (Tab::Decryption, InputMode::Overwrite) => OVERWRITE,
+        (Tab::Xor, _) => XOR,
+        (Tab::Statistics, _) => STATISTICS,
+        (Tab::Forge, InputMode::Normal) => FORGE_NORMAL,
+        (Tab::Forge, InputMode::Editing) => FORGE_EDITING,
+        _ => &[],
+    }
+}
+
+// Every binding available in a tab and mode, the specific ones first
+pub fn bindings(tab: &Tab, mode: &InputMode) -> Vec<&'static Binding> {
+    let mut bindings: Vec<&Binding> = local_bindings(tab, mode).iter().collect();
+    if let InputMode::Normal = mode {
+        bindings.extend(GLOBAL_NORMAL);
+    }
+    bindings.extend(GLOBAL);
+    bindings
+}
+
+// The action bound to a key, if any
+pub fn action(tab: &Tab, mode: &InputMode, event: KeyEvent) -> Option<Action> {
+    let bindings = bindings(tab, mode);
+    let bound = bindings
+        .iter()
+        .find(|b| b.keys.iter().any(|key| key.matches(event)));
+    let typed = || match event.code {
+        KeyCode::Char(_) if (event.modifiers - KeyModifiers::SHIFT).is_empty() => {
+            bindings.iter().find(|b| b.keys.is_empty())
+        }
+        _ => None,
+    };
+    bound.or_else(typed).map(|b| b.action)
+}
+
+fn key_name(key: Key) -> String {
+    let name = match key.code {
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::PageUp => "PgUp".to_string(),
+        KeyCode::PageDown => "PgDn".to_string(),
+        KeyCode::Delete => "Del".to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        code => format!("{:?}", code),
+    };
+    match key.modifiers.contains(KeyModifiers::CONTROL) {
+        true => format!("Ctrl+{}", name),
+        false => name,
+    }
+}
+
+// How the keys of a binding are written in the help
+pub fn key_names(binding: &Binding) -> String {
+    match binding.keys.is_empty() {
+        true => "text".to_string(),
+        false => binding
+            .keys
+            .iter()
+            .map(|key| key_name(*key))
+            .collect::<Vec<_>>()
+            .join("/"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action() {
+        let normal = InputMode::Normal;
+        let editing = InputMode::Editing;
+        assert_eq!(
+            action(
+                &Tab::Decryption,
+                &normal,
+                KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE)
+            ),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            action(
+                &Tab::Decryption,
+                &editing,
+                KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE)
+            ),
+            Some(Action::Type)
+        );
+        assert_eq!(
+            action(
+                &Tab::Decryption,
+                &editing,
+                KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)
+            ),
+            Some(Action::Confirm)
+        );
+        assert_eq!(
+            action(
+                &Tab::Decryption,
+                &editing,
+                KeyEvent::new(KeyCode::F(5), KeyModifiers::NONE)
+            ),
+            None
+        );
+        assert_eq!(
+            action(
+                &Tab::Decryption,
+                &InputMode::Search,
+                KeyEvent::new(KeyCode::F(1), KeyModifiers::NONE)
+            ),
+            Some(Action::Help)
+        );
+        assert_eq!(
+            action(
+                &Tab::Forge,
+                &editing,
+                KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE)
+            ),
+            Some(Action::NextTab)
+        );
+        assert_eq!(
+            action(
+                &Tab::Xor,
+                &normal,
+                KeyEvent::new(KeyCode::Char('z'), KeyModifiers::NONE)
+            ),
+            None
+        );
+        assert_eq!(
+            action(
+                &Tab::Decryption,
+                &normal,
+                KeyEvent::new(KeyCode::Char('N'), KeyModifiers::SHIFT)
+            ),
+            Some(Action::PreviousMatch)
+        );
+        assert_eq!(
+            action(
+                &Tab::Encrypted,
+                &editing,
+                KeyEvent::new(KeyCode::Left, KeyModifiers::CONTROL)
+            ),
+            Some(Action::WordLeft)
+        );
+        assert_eq!(
+            action(
+                &Tab::Encrypted,
+                &editing,
+                KeyEvent::new(KeyCode::Char('a'), KeyModifiers::CONTROL)
+            ),
+            None
+        );
+
+        // A key may only be bound once in every tab and mode
+        let tabs = [
+            Tab::Encrypted,
+            Tab::Decryption,
+            Tab::Xor,
+            Tab::Statistics,
+            Tab::Forge,
+        ];
+        let modes = [
+            InputMode::Normal,
+            InputMode::Editing,
+            InputMode::KnownPlaintext,
+            InputMode::Export,
+            InputMode::Search,
+            InputMode::Hunt,
+            InputMode::BulkPaste,
+            InputMode::Overwrite,
+            InputMode::Signatures,
+            InputMode::Verify,
+            InputMode::Patterns,
+        ];
+        for tab in &tabs {
+            for mode in &modes {
+                let keys: Vec<Key> = bindings(tab, mode)
+                    .iter()
+                    .flat_map(|b| b.keys.iter().copied())
+                    .collect();
+                for (i, key) in keys.iter().enumerate() {
+                    assert!(!keys[i + 1..].contains(key), "{:?} bound twice", key);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,283 @@
+// Known bytes a structure must explain before it is trusted
+const MIN_AGREEMENT: usize = 8;
+
+// Consecutive matching bytes that make a repeated block
+const MIN_BLOCK: usize = 8;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PatternKind {
+    // The whole key repeats with this period
+    Period(usize),
+    // key[start..start + len] reappears 'shift' bytes later
+    Block {
+        start: usize,
+        len: usize,
+        shift: usize,
+    },
+    // Each byte is a * previous + c mod 256
+    Lcg {
+        a: u8,
+        c: u8,
+    },
+}
+
+// Structure found in the known key bytes and the unknown bytes it predicts
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    // Known bytes or pairs of bytes that agree with it
+    pub agreement: usize,
+    pub extension: Vec<(usize, u8)>,
+}
+
+impl Pattern {
+    pub fn describe(&self) -> String {
+        match self.kind {
+            PatternKind::Period(p) => format!("repeats every {} bytes", p),
+            PatternKind::Block { start, len, shift } => format!(
+                "bytes {}..{} repeat {} bytes later",
+                start,
+                start + len,
+                shift
+            ),
+            PatternKind::Lcg { a, c } => format!("LCG x * {} + {} mod 256", a, c),
+        }
+    }
+}
+
+// Periods the known bytes agree with, smallest first, skipping multiples of
+// a period already found. A few disagreeing bytes are tolerated, as some
+// recovered bytes may be wrong; an unknown byte is only predicted when the
+// known bytes of its residue class are unanimous.
+fn periods(key: &[Option<u8>]) -> Vec<Pattern> {
+    let mut found: Vec<Pattern> = Vec::new();
+    for p in 1..=key.len() / 2 {
+        let is_multiple = found.iter().any(|f| match f.kind {
+            PatternKind::Period(q) => p % q == 0,
+            _ => false,
+        });
+        if is_multiple {
+            continue;
+        }
+        let (mut agree, mut conflicts) = (0, 0);
+        for j in 0..key.len() - p {
+            if let (Some(a), Some(b)) = (key[j], key[j + p]) {
+                match a == b {
+                    true => agree += 1,
+                    false => conflicts += 1,
+                }
+            }
+        }
+        if agree < MIN_AGREEMENT || conflicts * 10 > agree {
+            continue;
+        }
+        let mut extension = Vec::new();
+        for residue in 0..p {
+            let class: Vec<u8> = (residue..key.len())
+                .step_by(p)
+                .filter_map(|j| key[j])
+                .collect();
+            let byte = match class.first() {
+                Some(byte) if class.iter().all(|b| b == byte) => *byte,
+                _ => continue,
+            };
+            for j in (residue..key.len()).step_by(p) {
+                if key[j].is_none() {
+                    extension.push((j, byte));
+                }
+            }
+        }
+        extension.sort();
+        found.push(Pattern {
+            kind: PatternKind::Period(p),
+            agreement: agree,
+            extension,
+        });
+    }
+    found
+}
+
+// Copy bytes across 'shift' from 'from' outwards in the direction 'step'
+// while the two sides do not contradict each other
+fn extend_block(key: &[Option<u8>], shift: usize, from: isize, step: isize) -> Vec<(usize, u8)> {
+    let mut extension = Vec::new();
+    let mut j = from;
+    while j >= 0 && (j as usize) + shift < key.len() {
+        let (a, b) = (j as usize, j as usize + shift);
+        match (key[a], key[b]) {
+            (Some(x), Some(y)) if x != y => break,
+            (Some(x), None) => extension.push((b, x)),
+            (None, Some(y)) => extension.push((a, y)),
+            (None, None) => break,
+            _ => {}
+        }
+        j += step;
+    }
+    extension
+}
+
+// Runs of known bytes that reappear at another offset, other than at a
+// period of the whole key. The repeat is assumed to carry on past the run
+// until the two copies disagree.
+fn blocks(key: &[Option<u8>], periods: &[usize]) -> Vec<Pattern> {
+    let mut found = Vec::new();
+    for shift in 1..key.len() {
+        if periods.iter().any(|p| shift % p == 0) {
+            continue;
+        }
+        let mut run = 0;
+        for j in 0..=key.len() - shift {
+            let same = j + shift < key.len()
+                && matches!((key[j], key[j + shift]), (Some(a), Some(b)) if a == b);
+            if same {
+                run += 1;
+                continue;
+            }
+            if run >= MIN_BLOCK {
+                let start = j - run;
+                let mut extension = extend_block(key, shift, start as isize - 1, -1);
+                extension.extend(extend_block(key, shift, j as isize, 1));
+                extension.sort();
+                found.push(Pattern {
+                    kind: PatternKind::Block {
+                        start,
+                        len: run,
+                        shift,
+                    },
+                    agreement: run,
+                    extension,
+                });
+            }
+            run = 0;
+        }
+    }
+    found
+}
+
+// Inverse of an odd multiplier mod 256
+fn inverse(a: u8) -> Option<u8> {
+    (1..=255u8).find(|x| a.wrapping_mul(*x) == 1)
+}
+
+// A byte-wise linear congruential generator fitting every pair of
+// consecutive known bytes
+fn lcg(key: &[Option<u8>]) -> Option<Pattern> {
+    let pairs: Vec<(u8, u8)> = key
+        .windows(2)
+        .filter_map(|w| Some((w[0]?, w[1]?)))
+        .collect();
+    if pairs.len() < MIN_AGREEMENT {
+        return None;
+    }
+    let (x0, y0) = pairs[0];
+    let (a, c) = (0..=255u8)
+        // a constant key is found as period 1
+        .filter(|a| *a != 0)
+        .map(|a| (a, y0.wrapping_sub(a.wrapping_mul(x0))))
+        .filter(|(a, c)| !(*a == 1 && *c == 0))
+        .find(|(a, c)| {
+            pairs
+                .iter()
+                .all(|(x, y)| a.wrapping_mul(*x).wrapping_add(*c) == *y)
+        })?;
+
+    // Run the generator forwards from every known byte, and backwards when
+    // the multiplier can be inverted
+    let mut predicted = key.to_vec();
+    for j in 1..key.len() {
+        if predicted[j].is_none() {
+            predicted[j] = predicted[j - 1].map(|x| a.wrapping_mul(x).wrapping_add(c));
+        }
+    }
+    if let Some(a_inv) = inverse(a) {
+        for j in (0..key.len() - 1).rev() {
+            if predicted[j].is_none() {
+                predicted[j] = predicted[j + 1].map(|y| a_inv.wrapping_mul(y.wrapping_sub(c)));
+            }
+        }
+    }
+    let extension = (0..key.len())
+        .filter(|j| key[*j].is_none())
+        .filter_map(|j| Some((j, predicted[j]?)))
+        .collect();
+    Some(Pattern {
+        kind: PatternKind::Lcg { a, c },
+        agreement: pairs.len(),
+        extension,
+    })
+}
+
+// Every structure found in the known key bytes, best supported first
+pub fn analyze(key: &[Option<u8>]) -> Vec<Pattern> {
+    let mut patterns = periods(key);
+    let found: Vec<usize> = patterns
+        .iter()
+        .filter_map(|p| match p.kind {
+            PatternKind::Period(p) => Some(p),
+            _ => None,
+        })
+        .collect();
+    patterns.extend(blocks(key, &found));
+    patterns.extend(lcg(key));
+    patterns.sort_by_key(|p| std::cmp::Reverse(p.agreement));
+    patterns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze() {
+        // A 5 byte key repeated, with gaps
+        let mut key: Vec<Option<u8>> = (0..40).map(|j| Some([9, 200, 31, 4, 77][j % 5])).collect();
+        for j in [3, 8, 21, 39] {
+            key[j] = None;
+        }
+        let patterns = analyze(&key);
+        assert_eq!(patterns[0].kind, PatternKind::Period(5));
+        assert_eq!(
+            patterns[0].extension,
+            vec![(3, 4), (8, 4), (21, 200), (39, 77)]
+        );
+
+        // A block of random bytes pasted twice
+        let random: Vec<u8> = (0..60u32).map(|j| (j * 7919 % 251) as u8).collect();
+        let mut key: Vec<Option<u8>> = random.iter().copied().map(Some).collect();
+        for j in 0..12 {
+            key[40 + j] = key[10 + j];
+        }
+        key[9] = None;
+        key[39] = None;
+        key[52] = None;
+        let patterns = analyze(&key);
+        let block = patterns
+            .iter()
+            .find(|p| matches!(p.kind, PatternKind::Block { shift: 30, .. }))
+            .unwrap();
+        assert_eq!(block.extension, vec![(52, random[22])]);
+
+        // Bytes from x * 5 + 3 mod 256
+        let mut x = 17u8;
+        let mut key: Vec<Option<u8>> = (0..30)
+            .map(|_| {
+                x = x.wrapping_mul(5).wrapping_add(3);
+                Some(x)
+            })
+            .collect();
+        let truth = key.clone();
+        for j in [0, 1, 14, 29] {
+            key[j] = None;
+        }
+        let pattern = analyze(&key)
+            .into_iter()
+            .find(|p| matches!(p.kind, PatternKind::Lcg { .. }))
+            .unwrap();
+        assert_eq!(pattern.kind, PatternKind::Lcg { a: 5, c: 3 });
+        assert!(pattern
+            .extension
+            .iter()
+            .all(|(j, byte)| truth[*j] == Some(*byte)));
+        assert_eq!(pattern.extension.len(), 4);
+    }
+}
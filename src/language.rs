@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+
+// Counts of the characters seen after one context, with their sum
+#[derive(Clone, Copy)]
+struct Counts {
+    counts: [u32; 256],
+    total: u32,
+}
+
+impl Counts {
+    const EMPTY: Counts = Counts {
+        counts: [0; 256],
+        total: 0,
+    };
+
+    fn add(&mut self, c: u8) {
+        self.counts[c as usize] += 1;
+        self.total += 1;
+    }
+}
+
+// Character trigram model with interpolated bigram and unigram estimates
+pub struct LanguageModel {
+    unigrams: Counts,
+    bigrams: HashMap<u8, Counts>,
+    trigrams: HashMap<[u8; 2], Counts>,
+}
+
+impl LanguageModel {
+    pub fn new(corpus: &str) -> LanguageModel {
+        let mut model = LanguageModel {
+            unigrams: Counts::EMPTY,
+            bigrams: HashMap::new(),
+            trigrams: HashMap::new(),
+        };
+        let text: Vec<u8> = corpus
+            .bytes()
+            .map(|c| if c.is_ascii_whitespace() { b' ' } else { c })
+            .collect();
+        for (i, &c) in text.iter().enumerate() {
+            model.unigrams.add(c);
+            if i >= 1 {
+                model
+                    .bigrams
+                    .entry(text[i - 1])
+                    .or_insert(Counts::EMPTY)
+                    .add(c);
+            }
+            if i >= 2 {
+                let context = [text[i - 2], text[i - 1]];
+                model
+                    .trigrams
+                    .entry(context)
+                    .or_insert(Counts::EMPTY)
+                    .add(c);
+            }
+        }
+        model
+    }
+
+    // Probability of 'c' following 'context', whose bytes may be unknown
+    pub fn probability(&self, context: &[Option<u8>], c: u8) -> f64 {
+        let estimate = |counts: Option<&Counts>| match counts {
+            Some(counts) => {
+                (counts.counts[c as usize] as f64 + 0.01) / (counts.total as f64 + 2.56)
+            }
+            None => 1.0 / 256.0,
+        };
+        let last = context.last().copied().flatten();
+        let before_last = match context.len() {
+            n if n >= 2 => context[n - 2],
+            _ => None,
+        };
+        let unigram = estimate(Some(&self.unigrams));
+        let bigram = last.map(|a| estimate(self.bigrams.get(&a)));
+        let trigram = match (before_last, last) {
+            (Some(a), Some(b)) => Some(estimate(self.trigrams.get(&[a, b]))),
+            _ => None,
+        };
+        match (trigram, bigram) {
+            (Some(t), Some(b)) => 0.6 * t + 0.3 * b + 0.1 * unigram,
+            (None, Some(b)) => 0.75 * b + 0.25 * unigram,
+            _ => unigram,
+        }
+    }
+
+    // Most likely printable character to follow 'context'
+    pub fn most_likely(&self, context: &[Option<u8>]) -> u8 {
+        (b' '..=b'~')
+            .max_by(|a, b| {
+                self.probability(context, *a)
+                    .total_cmp(&self.probability(context, *b))
+            })
+            .unwrap()
+    }
+}
+
+// Guess the key bytes of the columns covered by a single message, in order, by
+// extending that message with the most likely characters. 'key' holds the
+// known key bytes and 'single' says which columns to guess, among those only
+// one message covers.
+pub fn guess_single_coverage(
+    model: &LanguageModel,
+    encrypted_messages: &[Vec<u8>],
+    enabled: &[bool],
+    key: &[Option<u8>],
+    single: &[bool],
+) -> Vec<(usize, u8)> {
+    let mut key = key.to_vec();
+    let mut guesses = Vec::new();
+    for j in 0..key.len() {
+        if !single[j] || key[j].is_some() {
+            continue;
+        }
+        let msg = encrypted_messages
+            .iter()
+            .zip(enabled)
+            .find(|(m, enabled)| **enabled && m.len() > j)
+            .map(|(m, _)| m);
+        if let Some(msg) = msg {
+            let context: Vec<Option<u8>> = (j.saturating_sub(2)..j)
+                .map(|i| key[i].map(|k| msg[i] ^ k))
+                .collect();
+            let byte = msg[j] ^ model.most_likely(&context);
+            key[j] = Some(byte);
+            guesses.push((j, byte));
+        }
+    }
+    guesses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_most_likely() {
+        let model = LanguageModel::new("the cat then the dog then the end");
+        assert_eq!(model.most_likely(&[Some(b't'), Some(b'h')]), b'e');
+        assert_eq!(model.most_likely(&[None, Some(b'q')]), b' ');
+    }
+}
@@ -0,0 +1,130 @@
+// A single line of text with a cursor, for input boxes that need more than
+// appending characters
+#[derive(Default)]
+pub struct LineEditor {
+    text: Vec<char>,
+    // Index of the character the cursor is on, text.len() at the end
+    cursor: usize,
+}
+
+impl LineEditor {
+    pub fn text(&self) -> String {
+        self.text.iter().collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += 1;
+    }
+
+    // Insert pasted text, joining its lines
+    pub fn paste(&mut self, text: &str) {
+        for c in text.chars().filter(|c| *c != '\n' && *c != '\r') {
+            self.insert(c);
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.text.remove(self.cursor);
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if self.cursor < self.text.len() {
+            self.text.remove(self.cursor);
+        }
+    }
+
+    pub fn left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.text.len());
+    }
+
+    pub fn home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.cursor = self.text.len();
+    }
+
+    // Words are runs of alphanumeric characters
+    pub fn word_left(&mut self) {
+        while self.cursor > 0 && !self.text[self.cursor - 1].is_alphanumeric() {
+            self.cursor -= 1;
+        }
+        while self.cursor > 0 && self.text[self.cursor - 1].is_alphanumeric() {
+            self.cursor -= 1;
+        }
+    }
+
+    pub fn word_right(&mut self) {
+        let n = self.text.len();
+        while self.cursor < n && self.text[self.cursor].is_alphanumeric() {
+            self.cursor += 1;
+        }
+        while self.cursor < n && !self.text[self.cursor].is_alphanumeric() {
+            self.cursor += 1;
+        }
+    }
+
+    // The text cut into rows of 'width' characters, and the row and column of
+    // the cursor in them. A cursor at the end of a full row goes to the next one.
+    pub fn wrap(&self, width: usize) -> (Vec<String>, (usize, usize)) {
+        let width = width.max(1);
+        let mut rows: Vec<String> = self
+            .text
+            .chunks(width)
+            .map(|row| row.iter().collect())
+            .collect();
+        let cursor = (self.cursor / width, self.cursor % width);
+        if rows.len() <= cursor.0 {
+            rows.push(String::new());
+        }
+        (rows, cursor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_editor() {
+        let mut editor = LineEditor::default();
+        editor.paste("dead\nbeef");
+        assert_eq!(editor.text(), "deadbeef");
+        editor.home();
+        editor.right();
+        editor.insert('0');
+        editor.delete();
+        assert_eq!(editor.text(), "d0adbeef");
+        editor.end();
+        editor.backspace();
+        assert_eq!(editor.text(), "d0adbee");
+
+        editor.clear();
+        editor.paste("00 11:22");
+        editor.word_left();
+        assert_eq!(editor.cursor, 6);
+        editor.word_left();
+        assert_eq!(editor.cursor, 3);
+        editor.word_right();
+        assert_eq!(editor.cursor, 6);
+
+        editor.end();
+        let (rows, cursor) = editor.wrap(4);
+        assert_eq!(rows, vec!["00 1", "1:22", ""]);
+        assert_eq!(cursor, (2, 0));
+    }
+}
@@ -1,28 +1,63 @@
 mod app_state;
+mod benchmark;
+mod challenge;
+mod cli;
 mod decryption;
+mod export;
+mod forge;
+mod hunt;
+mod keymap;
+mod keystream;
+mod language;
+mod line_editor;
+mod nonce;
+mod plaintext;
+mod propagation;
+mod search;
+mod signatures;
+mod solver;
+mod statistics;
 mod ui;
 mod util;
 
-use app_state::{App, InputMode, Tab};
+use app_state::{App, ForgeField, InputMode, Level, Tab, DEFAULT_FILES_PREFIX, WEIGHT_STEP};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyCode, KeyEvent, KeyModifiers,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use decryption::decrypt_key;
-use std::{error::Error, io};
+use decryption::FULL_WEIGHT;
+use keymap::Action;
+use std::{env, error::Error, io, process};
 use tui::{
     backend::{Backend, CrosstermBackend},
     Terminal,
 };
 use ui::ui;
-use util::decode_hex;
+use util::{decode_hex_line, encode_hex, hex_error};
 
 fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(err) = cli::run(&args) {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
@@ -35,7 +70,8 @@ fn main() -> Result<(), Box<dyn Error>> {
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste
     )?;
     terminal.show_cursor()?;
 
@@ -49,130 +85,741 @@ fn main() -> Result<(), Box<dyn Error>> {
 fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<()> {
     loop {
         terminal.draw(|f| ui(f, app))?;
-        if let Event::Key(key) = event::read()? {
-            match key.code {
-                KeyCode::Char('q') => return Ok(()),
-                KeyCode::Tab => app.toggle_tab(),
-                _ => match app.current_tab {
-                    Tab::Encrypted => match app.input_mode {
-                        InputMode::Normal => match key.code {
-                            KeyCode::Right => app.toggle_tab(),
-                            KeyCode::Left => app.toggle_tab(),
-                            KeyCode::Char('e') => app.input_mode = InputMode::Editing,
-                            _ => {}
-                        },
-                        InputMode::Editing => match key.code {
-                            KeyCode::Right => match app.current_tab {
-                                Tab::Encrypted => {
-                                    if app.position.1 < app.input.len() {
-                                        app.position.1 += 1
-                                    }
-                                }
-                                Tab::Decryption => app.position.1 += 1,
-                            },
-                            KeyCode::Left => {
-                                if app.position.1 > 0 {
-                                    app.position.1 -= 1
-                                }
-                            }
-                            KeyCode::Up => {
-                                if app.position.0 > 0 {
-                                    app.position.0 -= 1
-                                }
-                            }
-                            KeyCode::Down => app.position.0 += 1,
-                            KeyCode::Char(c) => {
-                                app.input.push(c);
-                                app.position.1 += 1;
-                            }
-                            KeyCode::Backspace => {
-                                app.input.pop();
-                                if app.position.1 > 0 {
-                                    app.position.1 -= 1;
-                                }
-                            }
-                            KeyCode::Enter => {
-                                if app.input.len() % 2 == 0 {
-                                    if let Ok(msg_bytes) = decode_hex(&app.input) {
-                                        app.encrypted_messages.push(msg_bytes);
-                                        app.decrypted_messages.push(vec![
-                                            0;
-                                            app.encrypted_messages
-                                                .last()
-                                                .unwrap()
-                                                .len()
-                                        ]);
-                                        app.key = decrypt_key(&app.encrypted_messages);
-                                        app.input_mode = InputMode::Normal;
-                                    }
-                                }
-                                app.input.clear();
-                                app.position = (0, 0);
-                            }
-                            KeyCode::Esc => {
-                                app.input_mode = InputMode::Normal;
-                            }
-                            _ => {}
-                        },
-                    },
-                    Tab::Decryption => match app.input_mode {
-                        InputMode::Normal => {
-                            if let KeyCode::Char('e') = key.code {
-                                app.input_mode = InputMode::Editing;
-                            }
+        match event::read()? {
+            Event::Key(key) if handle_key(app, key) => return Ok(()),
+            Event::Paste(text) => match (&app.current_tab, &app.input_mode) {
+                // Several lines are several ciphertexts
+                (Tab::Encrypted, InputMode::Normal | InputMode::Editing)
+                    if text.lines().filter(|l| !l.trim().is_empty()).count() > 1 =>
+                {
+                    app.start_bulk_paste(&text)
+                }
+                (Tab::Encrypted, InputMode::Editing) => app.add_input.paste(&text),
+                // Other text fields take pasted text as if it were typed. In the
+                // other modes the characters would trigger commands.
+                (
+                    _,
+                    InputMode::Editing
+                    | InputMode::KnownPlaintext
+                    | InputMode::Search
+                    | InputMode::Hunt
+                    | InputMode::Overwrite
+                    | InputMode::Verify
+                    | InputMode::Export,
+                ) => {
+                    for c in text.chars().filter(|c| !c.is_control()) {
+                        handle_key(app, KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+                    }
+                }
+                _ => app.notify(Level::Error, "Nothing to paste into here".to_string()),
+            },
+            _ => {}
+        }
+    }
+}
+
+// Act on a key press; returns whether to quit
+fn handle_key(app: &mut App, key: KeyEvent) -> bool {
+    // Any key closes the popups
+    if app.show_help || app.show_notifications {
+        app.show_help = false;
+        app.show_notifications = false;
+        return false;
+    }
+    let action = match keymap::action(&app.current_tab, &app.input_mode, key) {
+        Some(action) => action,
+        None => return false,
+    };
+    match action {
+        Action::Quit => return true,
+        Action::NextTab => app.toggle_tab(),
+        Action::PreviousTab => app.toggle_tab_back(),
+        Action::Help => app.show_help = true,
+        Action::Notifications => app.show_notifications = true,
+        _ => match app.current_tab {
+            Tab::Encrypted => handle_encrypted(app, action, key.code),
+            Tab::Decryption => handle_decryption(app, action, key.code),
+            Tab::Xor => handle_xor(app, action),
+            Tab::Statistics => handle_statistics(app, action),
+            Tab::Forge => handle_forge(app, action, key.code),
+        },
+    }
+    false
+}
+
+fn handle_encrypted(app: &mut App, action: Action, code: KeyCode) {
+    match app.input_mode {
+        InputMode::Normal => match action {
+            Action::Edit => app.input_mode = InputMode::Editing,
+            Action::Up if app.selected_message > 0 => app.selected_message -= 1,
+            Action::Down if app.selected_message + 1 < app.encrypted_messages.len() => {
+                app.selected_message += 1
+            }
+            Action::ToggleMessage if !app.encrypted_messages.is_empty() => {
+                let i = app.selected_message;
+                app.toggle_message(i);
+                let state = match app.disabled_messages[i] {
+                    true => "Disabled",
+                    false => "Enabled",
+                };
+                let text = format!("{} message {}, {}", state, i, app.key_summary());
+                app.notify(Level::Info, text);
+            }
+            Action::Remove if !app.encrypted_messages.is_empty() => {
+                let i = app.selected_message;
+                app.remove_message(i);
+                let text = format!("Removed message {}, {}", i, app.key_summary());
+                app.notify(Level::Info, text);
+            }
+            Action::NextNonceLength => {
+                app.next_nonce_len();
+                let text = match app.nonce_len {
+                    0 => "New ciphertexts have no nonce prefix".to_string(),
+                    n => format!("Stripping a {} byte nonce prefix from new ciphertexts", n),
+                };
+                app.notify(Level::Info, text);
+            }
+            Action::IsolateNonce if !app.encrypted_messages.is_empty() => {
+                app.isolate_nonce_group();
+                let text = format!(
+                    "Solving the {} messages sharing the nonce of message {}, {}",
+                    app.nonce_group(app.selected_message).len(),
+                    app.selected_message,
+                    app.key_summary()
+                );
+                app.notify(Level::Info, text);
+            }
+            Action::IncreaseWeight | Action::DecreaseWeight
+                if !app.encrypted_messages.is_empty() =>
+            {
+                let i = app.selected_message;
+                let weight = match action {
+                    Action::IncreaseWeight => {
+                        (app.message_weights[i] + WEIGHT_STEP).min(FULL_WEIGHT)
+                    }
+                    _ => app.message_weights[i].saturating_sub(WEIGHT_STEP),
+                };
+                app.set_message_weight(i, weight);
+                let text = format!("Message {} weighs {}%, {}", i, weight, app.key_summary());
+                app.notify(Level::Info, text);
+            }
+            Action::WeighByReadability => {
+                let changed = app.weigh_messages_by_readability();
+                let text = format!(
+                    "Reweighed {} messages by readability, {}",
+                    changed,
+                    app.key_summary()
+                );
+                app.notify(Level::Info, text);
+            }
+            _ => {}
+        },
+        InputMode::Editing => match action {
+            Action::Left => app.add_input.left(),
+            Action::Right => app.add_input.right(),
+            Action::WordLeft => app.add_input.word_left(),
+            Action::WordRight => app.add_input.word_right(),
+            Action::Home => app.add_input.home(),
+            Action::End => app.add_input.end(),
+            Action::Type => {
+                if let KeyCode::Char(c) = code {
+                    app.add_input.insert(c);
+                    app.add_error = None;
+                }
+            }
+            Action::Backspace => {
+                app.add_input.backspace();
+                app.add_error = None;
+            }
+            Action::Remove => {
+                app.add_input.delete();
+                app.add_error = None;
+            }
+            // Invalid input stays in the box with the offending character marked
+            Action::Confirm => match hex_error(&app.add_input.text()) {
+                Some((position, err)) => {
+                    app.add_error = Some(position);
+                    let text = format!("Invalid ciphertext: {} at column {}", err, position + 1);
+                    app.notify(Level::Error, text);
+                }
+                None => {
+                    let ciphertext = decode_hex_line(&app.add_input.text()).unwrap_or_default();
+                    app.add_ciphertext(ciphertext);
+                    let i = app.encrypted_messages.len() - 1;
+                    let text = format!(
+                        "Added message {} ({} bytes, nonce shared by {} messages), {}",
+                        i,
+                        app.encrypted_messages[i].len(),
+                        app.nonce_group(i).len(),
+                        app.key_summary()
+                    );
+                    app.notify(Level::Info, text);
+                    app.add_input.clear();
+                    app.input_mode = InputMode::Normal;
+                }
+            },
+            Action::Cancel => app.input_mode = InputMode::Normal,
+            _ => {}
+        },
+        InputMode::BulkPaste => match action {
+            Action::Confirm => {
+                let (accepted, rejected) =
+                    (app.bulk_paste.accepted.len(), app.bulk_paste.rejected.len());
+                app.commit_bulk_paste();
+                let text = format!(
+                    "Added {} pasted ciphertexts, skipped {} rejected lines, {}",
+                    accepted,
+                    rejected,
+                    app.key_summary()
+                );
+                app.notify(Level::Info, text);
+                app.input_mode = InputMode::Normal;
+            }
+            Action::Cancel => {
+                app.bulk_paste = Default::default();
+                app.input_mode = InputMode::Normal;
+            }
+            _ => {}
+        },
+        InputMode::KnownPlaintext
+        | InputMode::Export
+        | InputMode::Search
+        | InputMode::Hunt
+        | InputMode::Overwrite
+        | InputMode::Signatures
+        | InputMode::Verify
+        | InputMode::Patterns => {}
+    }
+}
+
+fn handle_decryption(app: &mut App, action: Action, code: KeyCode) {
+    match app.input_mode {
+        InputMode::Normal => match action {
+            Action::Edit if !app.encrypted_messages.is_empty() => {
+                app.position.0 = app.position.0.min(app.encrypted_messages.len() - 1);
+                app.input_mode = InputMode::Editing;
+            }
+            Action::Undo => undo_key_edit(app),
+            Action::NextAlphabet => {
+                app.next_alphabet();
+                let text = format!("Alphabet: {}, {}", app.alphabet.name(), app.key_summary());
+                app.notify(Level::Info, text);
+            }
+            Action::TogglePropagation => {
+                app.toggle_propagation();
+                let state = if app.propagate { "on" } else { "off" };
+                let text = format!("Propagation {}, {}", state, app.key_summary());
+                app.notify(Level::Info, text);
+            }
+            Action::KnownPlaintext if !app.encrypted_messages.is_empty() => {
+                app.position.0 = app.position.0.min(app.encrypted_messages.len() - 1);
+                app.input.clear();
+                app.input_mode = InputMode::KnownPlaintext;
+            }
+            Action::Search => app.input_mode = InputMode::Search,
+            Action::NextMatch => app.next_match(),
+            Action::PreviousMatch => app.previous_match(),
+            Action::Hunt => {
+                app.update_hunt();
+                app.input_mode = InputMode::Hunt;
+            }
+            Action::Signatures => {
+                app.update_signatures();
+                app.input_mode = InputMode::Signatures;
+            }
+            Action::ToggleBinary => {
+                app.binary = !app.binary;
+                app.hex_digit = None;
+                let state = if app.binary { "on" } else { "off" };
+                app.notify(Level::Info, format!("Binary mode {}", state));
+            }
+            Action::WriteFiles => {
+                if app.plaintext_files().is_empty() {
+                    let text = format!(
+                        "No plaintext has {:.0}% of its key bytes confirmed yet",
+                        signatures::MIN_FILE_COVERAGE * 100.0
+                    );
+                    app.notify(Level::Error, text);
+                } else {
+                    app.input = DEFAULT_FILES_PREFIX.to_string();
+                    app.export_error = None;
+                    app.write_files = true;
+                    app.input_mode = InputMode::Export;
+                }
+            }
+            Action::Verify if !app.encrypted_messages.is_empty() => {
+                app.input.clear();
+                app.input_mode = InputMode::Verify;
+            }
+            Action::NextThreshold => {
+                app.next_agreement_threshold();
+                let text = format!(
+                    "Key bytes need {}% agreement, {}",
+                    app.solver.threshold(),
+                    app.key_summary()
+                );
+                app.notify(Level::Info, text);
+            }
+            Action::Patterns => {
+                app.update_patterns();
+                if app.patterns.is_empty() {
+                    app.notify(Level::Error, "No structure found in the key".to_string());
+                } else {
+                    app.input_mode = InputMode::Patterns;
+                }
+            }
+            Action::Export => {
+                app.input = app.default_export_path();
+                app.export_error = None;
+                app.write_files = false;
+                app.input_mode = InputMode::Export;
+            }
+            _ => {}
+        },
+        InputMode::Editing => match action {
+            // Moving away drops the guess under the cursor
+            Action::Right => {
+                app.pending_guess.clear();
+                app.hex_digit = None;
+                app.position.1 += 1
+            }
+            Action::Left if app.position.1 > 0 => {
+                app.pending_guess.clear();
+                app.hex_digit = None;
+                app.position.1 -= 1
+            }
+            Action::Up if app.position.0 > 0 => {
+                app.pending_guess.clear();
+                app.hex_digit = None;
+                app.position.0 -= 1
+            }
+            Action::Down if app.position.0 + 1 < app.encrypted_messages.len() => {
+                app.pending_guess.clear();
+                app.hex_digit = None;
+                app.position.0 += 1
+            }
+            Action::Type => {
+                if let (KeyCode::Char(c), Some(_)) = (code, app.cursor_column()) {
+                    let key = match app.binary {
+                        true => app.hex_guess_key(c),
+                        false => app.guess_key(c).map(Some),
+                    };
+                    match key {
+                        Ok(None) => {}
+                        Ok(Some(key)) if app.preview_guesses => app.pending_guess = key,
+                        Ok(Some(key)) => {
+                            let bytes: Vec<(usize, Option<u8>)> =
+                                key.iter().map(|(pos, byte)| (*pos, Some(*byte))).collect();
+                            app.set_manual_key_bytes(&bytes);
+                            app.position.1 += key.len();
+                        }
+                        Err(err) => {
+                            app.hex_digit = None;
+                            app.notify(Level::Error, format!("Cannot type here: {}", err))
                         }
-                        InputMode::Editing => match key.code {
-                            KeyCode::Right => match app.current_tab {
-                                Tab::Encrypted => {
-                                    if app.position.1 < app.input.len() {
-                                        app.position.1 += 1
-                                    }
-                                }
-                                Tab::Decryption => app.position.1 += 1,
-                            },
-                            KeyCode::Left => {
-                                if app.position.1 > 0 {
-                                    app.position.1 -= 1
-                                }
-                            }
-                            KeyCode::Up => {
-                                if app.position.0 > 0 {
-                                    app.position.0 -= 1
-                                }
-                            }
-                            KeyCode::Down => app.position.0 += 1,
-                            KeyCode::Char(c) => {
-                                let msg_id = app.position.0;
-                                let chars_before_msg = msg_id.to_string().len() + 2;
-                                let key_pos = app.position.1 as i32 - chars_before_msg as i32;
-                                if key_pos >= 0
-                                    && key_pos < app.encrypted_messages[msg_id].len() as i32
-                                {
-                                    app.key[key_pos as usize] = Some(
-                                        c as u8 ^ app.encrypted_messages[msg_id][key_pos as usize],
-                                    );
-                                    app.position.1 += 1;
-                                }
-                            }
-                            KeyCode::Backspace => {
-                                let msg_id = app.position.0;
-                                let chars_before_msg = msg_id.to_string().len() + 2;
-                                let key_pos = app.position.1 as i32 - chars_before_msg as i32 - 1;
-                                if key_pos >= 0
-                                    && key_pos < app.encrypted_messages[msg_id].len() as i32
-                                {
-                                    app.key[key_pos as usize] = None;
-                                    app.position.1 -= 1;
-                                }
-                            }
-                            KeyCode::Esc => {
-                                app.input_mode = InputMode::Normal;
-                            }
-                            _ => {}
-                        },
-                    },
+                    }
+                }
+            }
+            Action::TogglePreview => {
+                app.preview_guesses = !app.preview_guesses;
+                app.pending_guess.clear();
+                let state = if app.preview_guesses { "on" } else { "off" };
+                app.notify(Level::Info, format!("Guess preview {}", state));
+            }
+            Action::Confirm => {
+                if let Some(&(key_pos, _)) = app.pending_guess.first() {
+                    let (valid, invalid) = app.pending_guess_report();
+                    let key = std::mem::take(&mut app.pending_guess);
+                    let bytes: Vec<(usize, Option<u8>)> =
+                        key.iter().map(|(pos, byte)| (*pos, Some(*byte))).collect();
+                    app.set_manual_key_bytes(&bytes);
+                    app.position.1 += key.len();
+                    let text = format!(
+                        "Set {} key bytes at {} ({} other messages valid, {} invalid), {}",
+                        key.len(),
+                        key_pos,
+                        valid,
+                        invalid,
+                        app.key_summary()
+                    );
+                    app.notify(Level::Info, text);
+                }
+            }
+            Action::Overwrite => app.start_overwrite(),
+            Action::Undo => {
+                app.pending_guess.clear();
+                undo_key_edit(app);
+            }
+            Action::Cancel if !app.pending_guess.is_empty() => app.pending_guess.clear(),
+            Action::Backspace if !app.pending_guess.is_empty() => app.pending_guess.clear(),
+            // Clear the byte left of the cursor and move onto it
+            Action::Backspace if app.position.1 > 0 => {
+                app.position.1 -= 1;
+                match app.cursor_column() {
+                    Some(j) => app.set_manual_key_byte(j, None),
+                    None => app.position.1 += 1,
+                }
+            }
+            Action::Cancel => app.input_mode = InputMode::Normal,
+            _ => {}
+        },
+        InputMode::Overwrite => match action {
+            Action::Left if app.overwrite_range.1 > app.overwrite_range.0 => {
+                app.overwrite_range.1 -= 1
+            }
+            Action::Right
+                if app.overwrite_range.1 < app.encrypted_messages[app.position.0].len() =>
+            {
+                app.overwrite_range.1 = app.overwrite_range.1.max(app.overwrite_range.0) + 1
+            }
+            Action::Type => {
+                if let KeyCode::Char(c) = code {
+                    app.input.push(c);
+                }
+            }
+            Action::Backspace => {
+                app.input.pop();
+            }
+            Action::Confirm | Action::Force if app.input.is_empty() => {
+                app.notify(Level::Error, "Type the replacement text first".to_string())
+            }
+            Action::Confirm | Action::Force => match app.apply_overwrite(action == Action::Force) {
+                Ok(len) => {
+                    let text = format!(
+                        "Overwrote {} characters of message {}, {}",
+                        len,
+                        app.position.0,
+                        app.key_summary()
+                    );
+                    app.notify(Level::Info, text);
+                    // Leave the cursor after the replacement
+                    app.position.1 += app.input.len();
+                    app.input.clear();
+                    app.input_mode = InputMode::Editing;
+                }
+                Err(err) => {
+                    let hint = if action == Action::Confirm {
+                        ", Ctrl+F to force"
+                    } else {
+                        ""
+                    };
+                    app.notify(Level::Error, format!("Overwrite refused: {}{}", err, hint));
+                }
+            },
+            Action::Cancel => {
+                app.input.clear();
+                app.input_mode = InputMode::Editing;
+            }
+            _ => {}
+        },
+        InputMode::KnownPlaintext => match action {
+            Action::Up if app.position.0 > 0 => app.position.0 -= 1,
+            Action::Down if app.position.0 + 1 < app.encrypted_messages.len() => {
+                app.position.0 += 1
+            }
+            Action::Type => {
+                if let KeyCode::Char(c) = code {
+                    app.input.push(c);
+                }
+            }
+            Action::Backspace => {
+                app.input.pop();
+            }
+            Action::Confirm => {
+                app.apply_known_plaintext();
+                let text = format!(
+                    "Applied \"{}\" to message {}, {}",
+                    app.input,
+                    app.position.0,
+                    app.key_summary()
+                );
+                app.notify(Level::Info, text);
+                app.input.clear();
+                app.input_mode = InputMode::Normal;
+            }
+            Action::Cancel => {
+                app.input.clear();
+                app.input_mode = InputMode::Normal;
+            }
+            _ => {}
+        },
+        InputMode::Export => match action {
+            Action::Left | Action::Right if !app.write_files => {
+                let default_path = app.input == app.default_export_path();
+                app.export_format = match action {
+                    Action::Left => app.export_format.previous(),
+                    _ => app.export_format.next(),
+                };
+                if default_path {
+                    app.input = app.default_export_path();
+                }
+            }
+            Action::Up | Action::Down if !app.write_files => {
+                app.next_unknown_marker(action == Action::Down)
+            }
+            Action::Type => {
+                if let KeyCode::Char(c) = code {
+                    app.input.push(c);
+                }
+            }
+            Action::Backspace => {
+                app.input.pop();
+            }
+            Action::Confirm if app.write_files => match app.write_plaintext_files() {
+                Ok((paths, unknown)) => {
+                    let text = format!(
+                        "Wrote {} ({} unknown bytes written as zeros)",
+                        paths.join(", "),
+                        unknown
+                    );
+                    app.notify(Level::Info, text);
+                    app.input.clear();
+                    app.write_files = false;
+                    app.input_mode = InputMode::Normal;
+                }
+                Err(err) => {
+                    let text = format!("Could not write the plaintexts: {}", err);
+                    app.notify(Level::Error, text);
+                    app.export_error = Some(err.to_string());
+                }
+            },
+            Action::Confirm => match app.export() {
+                Ok(()) => {
+                    let text = format!("Wrote {}", app.input);
+                    app.notify(Level::Info, text);
+                    app.input.clear();
+                    app.input_mode = InputMode::Normal;
+                }
+                Err(err) => {
+                    let text = format!("Could not write {}: {}", app.input, err);
+                    app.notify(Level::Error, text);
+                    app.export_error = Some(err.to_string());
+                }
+            },
+            Action::Cancel => {
+                app.input.clear();
+                app.write_files = false;
+                app.input_mode = InputMode::Normal;
+            }
+            _ => {}
+        },
+        InputMode::Hunt => match action {
+            Action::Up if app.crib_index > 0 => app.crib_index -= 1,
+            Action::Down if app.crib_index + 1 < app.cribs.len() => app.crib_index += 1,
+            Action::Type => {
+                if let KeyCode::Char(c) = code {
+                    app.flag_pattern.push(c);
+                    app.update_hunt();
+                }
+            }
+            Action::Backspace => {
+                app.flag_pattern.pop();
+                app.update_hunt();
+            }
+            Action::Confirm => {
+                app.accept_crib();
+                let text = format!("Accepted crib, {}", app.key_summary());
+                app.notify(Level::Info, text);
+                app.input_mode = InputMode::Normal;
+            }
+            Action::Cancel => {
+                app.cribs.clear();
+                app.input_mode = InputMode::Normal;
+            }
+            _ => {}
+        },
+        InputMode::Verify => match action {
+            Action::Right => app.stream_cipher = app.stream_cipher.next(),
+            Action::Type => {
+                if let KeyCode::Char(c) = code {
+                    app.input.push(c);
+                }
+            }
+            Action::Backspace => {
+                app.input.pop();
+            }
+            Action::Confirm => match app.verify_keystream() {
+                Ok((agree, disagree, first)) => {
+                    let mut text = format!(
+                        "{} keystream: {} recovered bytes agree, {} disagree",
+                        app.stream_cipher.name(),
+                        agree,
+                        disagree
+                    );
+                    if let Some(j) = first {
+                        text.push_str(&format!(", first at column {}", j));
+                    }
+                    let level = if disagree == 0 {
+                        Level::Info
+                    } else {
+                        Level::Error
+                    };
+                    app.notify(level, text);
+                    app.input.clear();
+                    app.input_mode = InputMode::Normal;
+                }
+                Err(err) => app.notify(Level::Error, format!("Cannot verify: {}", err)),
+            },
+            Action::Cancel => {
+                app.input.clear();
+                app.input_mode = InputMode::Normal;
+            }
+            _ => {}
+        },
+        InputMode::Patterns => match action {
+            Action::Up if app.pattern_index > 0 => app.pattern_index -= 1,
+            Action::Down if app.pattern_index + 1 < app.patterns.len() => app.pattern_index += 1,
+            Action::Confirm => {
+                let set = app.accept_pattern();
+                let text = format!("Extended the key by {} bytes, {}", set, app.key_summary());
+                app.notify(Level::Info, text);
+                app.input_mode = InputMode::Normal;
+            }
+            Action::Cancel => {
+                app.patterns.clear();
+                app.input_mode = InputMode::Normal;
+            }
+            _ => {}
+        },
+        InputMode::Signatures => match action {
+            Action::Up if app.crib_index > 0 => app.crib_index -= 1,
+            Action::Down if app.crib_index + 1 < app.cribs.len() => app.crib_index += 1,
+            Action::Confirm => {
+                app.accept_crib();
+                let text = format!("Accepted file signature, {}", app.key_summary());
+                app.notify(Level::Info, text);
+                app.input_mode = InputMode::Normal;
+            }
+            Action::Cancel => {
+                app.cribs.clear();
+                app.input_mode = InputMode::Normal;
+            }
+            _ => {}
+        },
+        InputMode::Search => match action {
+            Action::Right => app.next_search_mode(),
+            Action::Type => {
+                if let KeyCode::Char(c) = code {
+                    app.search_pattern.push(c);
+                    app.update_search();
+                }
+            }
+            Action::Backspace => {
+                app.search_pattern.pop();
+                app.update_search();
+            }
+            Action::Confirm => {
+                app.first_match();
+                app.input_mode = InputMode::Normal;
+            }
+            Action::Cancel => {
+                app.clear_search();
+                app.input_mode = InputMode::Normal;
+            }
+            _ => {}
+        },
+        InputMode::BulkPaste => {}
+    }
+}
+
+fn undo_key_edit(app: &mut App) {
+    let text = match app.undo_key_edit() {
+        Some(len) => format!("Undid an edit of {} key bytes, {}", len, app.key_summary()),
+        None => "Nothing to undo".to_string(),
+    };
+    app.notify(Level::Info, text);
+}
+
+fn handle_xor(app: &mut App, action: Action) {
+    let n = app.encrypted_messages.len();
+    let len = app.xor_pair_bytes().map_or(0, |x| x.len());
+    match action {
+        Action::Up if app.xor_pair.0 > 0 => app.xor_pair.0 -= 1,
+        Action::Down if app.xor_pair.0 + 1 < n => app.xor_pair.0 += 1,
+        Action::PageUp if app.xor_pair.1 > 0 => app.xor_pair.1 -= 1,
+        Action::PageDown if app.xor_pair.1 + 1 < n => app.xor_pair.1 += 1,
+        Action::Left if app.xor_column > 0 => app.xor_column -= 1,
+        Action::Right if app.xor_column + 1 < len => app.xor_column += 1,
+        Action::Confirm if app.xor_column < len => app.jump_to_xor_column(),
+        _ => {}
+    }
+    app.xor_column = app.xor_column.min(len.saturating_sub(1));
+}
+
+fn handle_statistics(app: &mut App, action: Action) {
+    match action {
+        Action::Left if app.stats_column > 0 => app.stats_column -= 1,
+        Action::Right if app.stats_column + 1 < app.key.len() => app.stats_column += 1,
+        Action::Home => app.stats_column = 0,
+        Action::End => app.stats_column = app.key.len().saturating_sub(1),
+        _ => {}
+    }
+}
+
+fn handle_forge(app: &mut App, action: Action, code: KeyCode) {
+    match app.input_mode {
+        InputMode::Normal => match action {
+            Action::Edit => {
+                app.forge_field = ForgeField::Plaintext;
+                app.input.clear();
+                app.input_mode = InputMode::Editing;
+            }
+            Action::EditKey => {
+                app.forge_field = ForgeField::Key;
+                app.input = encode_hex(&app.forge_key);
+                app.input_mode = InputMode::Editing;
+            }
+            Action::EditForgery => {
+                app.forge_field = ForgeField::Forgery;
+                app.input = app.forge_plaintext.clone();
+                app.input_mode = InputMode::Editing;
+            }
+            Action::GenerateKey => app.generate_forge_key(),
+            _ => {}
+        },
+        InputMode::Editing => match action {
+            Action::Type => {
+                if let KeyCode::Char(c) = code {
+                    app.input.push(c);
+                }
+            }
+            Action::Backspace => {
+                app.input.pop();
+            }
+            Action::Confirm => match app.forge_field {
+                ForgeField::Plaintext => {
+                    app.encrypt_input();
+                    app.input.clear();
+                }
+                ForgeField::Key => match hex_error(&app.input) {
+                    Some((position, err)) => {
+                        let text = format!("Invalid key: {} at column {}", err, position + 1);
+                        app.notify(Level::Error, text);
+                    }
+                    None => {
+                        app.forge_key = decode_hex_line(&app.input).unwrap_or_default();
+                        let text = format!("Loaded a {} byte key", app.forge_key.len());
+                        app.notify(Level::Info, text);
+                        app.input.clear();
+                        app.input_mode = InputMode::Normal;
+                    }
                 },
+                ForgeField::Forgery => app.forge_input(),
+            },
+            Action::Cancel => {
+                app.input.clear();
+                app.input_mode = InputMode::Normal;
             }
-        }
+            _ => {}
+        },
+        InputMode::KnownPlaintext
+        | InputMode::Export
+        | InputMode::Search
+        | InputMode::Hunt
+        | InputMode::BulkPaste
+        | InputMode::Overwrite
+        | InputMode::Signatures
+        | InputMode::Verify
+        | InputMode::Patterns => {}
     }
 }
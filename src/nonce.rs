@@ -0,0 +1,238 @@
+use aes::{Aes128, Aes192, Aes256};
+use chacha20::{ChaCha20, ChaCha20Legacy};
+use ctr::{
+    cipher::{KeyIvInit, StreamCipher as Keystream},
+    Ctr128BE, Ctr32BE, Ctr64BE,
+};
+
+// Nonce or IV prefix lengths the import cycles through: none, 64-bit nonces,
+// 96-bit nonces and full AES-CTR counter blocks
+pub const NONCE_LENGTHS: [usize; 4] = [0, 8, 12, 16];
+
+// Stream ciphers a recovered keystream can be checked against
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StreamCipher {
+    // AES-128, -192 or -256 by key length. A 16 byte prefix is the initial
+    // big-endian counter block; a 12 or 8 byte nonce is followed by a 32 or
+    // 64-bit counter starting at 0
+    AesCtr,
+    // AES-GCM encryption: a 12 byte nonce followed by a 32-bit counter
+    // starting at 2
+    AesGcm,
+    // ChaCha20 from block 0, with a 12 byte IETF nonce or the original
+    // 8 byte nonce
+    ChaCha20,
+}
+
+impl StreamCipher {
+    pub fn next(self) -> StreamCipher {
+        match self {
+            StreamCipher::AesCtr => StreamCipher::AesGcm,
+            StreamCipher::AesGcm => StreamCipher::ChaCha20,
+            StreamCipher::ChaCha20 => StreamCipher::AesCtr,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StreamCipher::AesCtr => "AES-CTR",
+            StreamCipher::AesGcm => "AES-GCM",
+            StreamCipher::ChaCha20 => "ChaCha20",
+        }
+    }
+}
+
+fn apply<C: KeyIvInit + Keystream>(key: &[u8], iv: &[u8], stream: &mut [u8]) {
+    C::new_from_slices(key, iv)
+        .expect("key and IV lengths are checked")
+        .apply_keystream(stream)
+}
+
+// AES in counter mode from a full counter block whose last 'counter' bytes
+// are incremented
+fn aes_ctr(key: &[u8], iv: &[u8], counter: usize, stream: &mut [u8]) -> Result<(), usize> {
+    match (key.len(), counter) {
+        (16, 4) => apply::<Ctr32BE<Aes128>>(key, iv, stream),
+        (16, 8) => apply::<Ctr64BE<Aes128>>(key, iv, stream),
+        (16, _) => apply::<Ctr128BE<Aes128>>(key, iv, stream),
+        (24, 4) => apply::<Ctr32BE<Aes192>>(key, iv, stream),
+        (24, 8) => apply::<Ctr64BE<Aes192>>(key, iv, stream),
+        (24, _) => apply::<Ctr128BE<Aes192>>(key, iv, stream),
+        (32, 4) => apply::<Ctr32BE<Aes256>>(key, iv, stream),
+        (32, 8) => apply::<Ctr64BE<Aes256>>(key, iv, stream),
+        (32, _) => apply::<Ctr128BE<Aes256>>(key, iv, stream),
+        (n, _) => return Err(n),
+    }
+    Ok(())
+}
+
+// Split a ciphertext into its nonce prefix and the encrypted bytes
+pub fn split_nonce(ciphertext: &[u8], nonce_len: usize) -> (Vec<u8>, Vec<u8>) {
+    let (nonce, rest) = ciphertext.split_at(nonce_len.min(ciphertext.len()));
+    (nonce.to_vec(), rest.to_vec())
+}
+
+// Number each distinct nonce in order of first appearance; messages with the
+// same number share a keystream
+pub fn nonce_groups(nonces: &[Vec<u8>]) -> Vec<usize> {
+    let mut seen: Vec<&[u8]> = Vec::new();
+    nonces
+        .iter()
+        .map(
+            |nonce| match seen.iter().position(|n| *n == nonce.as_slice()) {
+                Some(group) => group,
+                None => {
+                    seen.push(nonce);
+                    seen.len() - 1
+                }
+            },
+        )
+        .collect()
+}
+
+// The first 'len' keystream bytes for a key and nonce
+pub fn keystream(
+    cipher: StreamCipher,
+    key: &[u8],
+    nonce: &[u8],
+    len: usize,
+) -> Result<Vec<u8>, String> {
+    let mut stream = vec![0; len];
+    let invalid = |what: &str, expected: &str, got: usize| {
+        format!(
+            "{} needs a {} byte {}, got {}",
+            cipher.name(),
+            expected,
+            what,
+            got
+        )
+    };
+    match cipher {
+        StreamCipher::AesCtr => {
+            let counter = match nonce.len() {
+                16 => 16,
+                12 => 4,
+                8 => 8,
+                n => return Err(invalid("nonce or IV", "8, 12 or 16", n)),
+            };
+            let mut iv = nonce.to_vec();
+            iv.resize(16, 0);
+            aes_ctr(key, &iv, counter, &mut stream)
+                .map_err(|n| invalid("key", "16, 24 or 32", n))?;
+        }
+        StreamCipher::AesGcm => {
+            if nonce.len() != 12 {
+                return Err(invalid("nonce", "12", nonce.len()));
+            }
+            let mut iv = nonce.to_vec();
+            iv.extend([0, 0, 0, 2]);
+            aes_ctr(key, &iv, 4, &mut stream).map_err(|n| invalid("key", "16, 24 or 32", n))?;
+        }
+        StreamCipher::ChaCha20 => {
+            if key.len() != 32 {
+                return Err(invalid("key", "32", key.len()));
+            }
+            match nonce.len() {
+                12 => apply::<ChaCha20>(key, nonce, &mut stream),
+                8 => apply::<ChaCha20Legacy>(key, nonce, &mut stream),
+                n => return Err(invalid("nonce", "8 or 12", n)),
+            }
+        }
+    }
+    Ok(stream)
+}
+
+// How many recovered key bytes agree with the expected keystream, how many
+// do not, and the first column that does not
+pub fn verify(recovered: &[Option<u8>], expected: &[u8]) -> (usize, usize, Option<usize>) {
+    let (mut agree, mut disagree, mut first) = (0, 0, None);
+    for (j, (k, e)) in recovered.iter().zip(expected).enumerate() {
+        match k {
+            Some(k) if k == e => agree += 1,
+            Some(_) => {
+                disagree += 1;
+                first = first.or(Some(j));
+            }
+            None => {}
+        }
+    }
+    (agree, disagree, first)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::decode_hex;
+
+    #[test]
+    fn test_keystream() {
+        // RFC 8439 section 2.4.2: block 1 of the keystream starts at byte 64
+        let key: Vec<u8> = (0..32).collect();
+        let nonce = decode_hex("000000000000004a00000000").unwrap();
+        let stream = keystream(StreamCipher::ChaCha20, &key, &nonce, 80).unwrap();
+        assert_eq!(
+            &stream[64..80],
+            decode_hex("224f51f3401bd9e12fde276fb8631ded").unwrap()
+        );
+
+        // NIST SP 800-38A F.5.1, first block of CTR-AES128
+        let key = decode_hex("2b7e151628aed2a6abf7158809cf4f3c").unwrap();
+        let iv = decode_hex("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff").unwrap();
+        let plaintext = decode_hex("6bc1bee22e409f96e93d7e117393172a").unwrap();
+        let stream = keystream(StreamCipher::AesCtr, &key, &iv, 16).unwrap();
+        let ciphertext: Vec<u8> = plaintext.iter().zip(&stream).map(|(p, k)| p ^ k).collect();
+        assert_eq!(
+            ciphertext,
+            decode_hex("874d6191b620e3261bef6864990db6ce").unwrap()
+        );
+        assert!(keystream(StreamCipher::AesCtr, &key, &iv[..10], 16).is_err());
+
+        // A 12 or 8 byte nonce is the start of a counter block counting from 0
+        let full = keystream(
+            StreamCipher::AesCtr,
+            &key,
+            &[iv[..12].to_vec(), vec![0; 4]].concat(),
+            48,
+        );
+        let short = keystream(StreamCipher::AesCtr, &key, &iv[..12], 48);
+        assert_eq!(full, short);
+        let full = keystream(
+            StreamCipher::AesCtr,
+            &key,
+            &[iv[..8].to_vec(), vec![0; 8]].concat(),
+            48,
+        );
+        let short = keystream(StreamCipher::AesCtr, &key, &iv[..8], 48);
+        assert_eq!(full, short);
+
+        // NIST GCM test case 3, first block of the ciphertext
+        let key = decode_hex("feffe9928665731c6d6a8f9467308308").unwrap();
+        let nonce = decode_hex("cafebabefacedbaddecaf888").unwrap();
+        let gcm = keystream(StreamCipher::AesGcm, &key, &nonce, 16).unwrap();
+        let plaintext = decode_hex("d9313225f88406e5a55909c5aff5269a").unwrap();
+        let ciphertext: Vec<u8> = plaintext.iter().zip(&gcm).map(|(p, k)| p ^ k).collect();
+        assert_eq!(
+            ciphertext,
+            decode_hex("42831ec2217774244b7221b784d0d49c").unwrap()
+        );
+
+        // The original ChaCha20 with an all-zero key and 8 byte nonce
+        let legacy = keystream(StreamCipher::ChaCha20, &[0; 32], &[0; 8], 16).unwrap();
+        assert_eq!(
+            legacy,
+            decode_hex("76b8e0ada0f13d90405d6ae55386bd28").unwrap()
+        );
+
+        let mut recovered: Vec<Option<u8>> = stream.iter().copied().map(Some).collect();
+        recovered[3] = None;
+        recovered[5] = Some(stream[5] ^ 1);
+        assert_eq!(verify(&recovered, &stream), (14, 1, Some(5)));
+
+        let (nonce, rest) = split_nonce(&[1, 2, 3, 4], 3);
+        assert_eq!((nonce, rest), (vec![1, 2, 3], vec![4]));
+        assert_eq!(
+            nonce_groups(&[vec![1], vec![2], vec![1], vec![]]),
+            vec![0, 1, 0, 2]
+        );
+    }
+}
@@ -0,0 +1,173 @@
+// Set of characters the plaintexts are expected to be drawn from
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Alphabet {
+    Printable,
+    Text,
+    Letters,
+}
+
+impl Alphabet {
+    pub fn next(self) -> Alphabet {
+        match self {
+            Alphabet::Printable => Alphabet::Text,
+            Alphabet::Text => Alphabet::Letters,
+            Alphabet::Letters => Alphabet::Printable,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Alphabet::Printable => "printable",
+            Alphabet::Text => "text",
+            Alphabet::Letters => "letters",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Alphabet> {
+        [Alphabet::Printable, Alphabet::Text, Alphabet::Letters]
+            .into_iter()
+            .find(|alphabet| alphabet.name() == name)
+    }
+
+    pub fn contains(self, c: u8) -> bool {
+        match self {
+            Alphabet::Printable => c == b' ' || c.is_ascii_graphic(),
+            Alphabet::Text => {
+                c == b' ' || c.is_ascii_alphanumeric() || b".,;:!?'\"-()".contains(&c)
+            }
+            Alphabet::Letters => c == b' ' || c.is_ascii_alphabetic(),
+        }
+    }
+}
+
+// Where a key byte came from
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KeyOrigin {
+    Unknown,
+    Solver,
+    Manual,
+    Guess,
+    Propagated,
+}
+
+impl KeyOrigin {
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyOrigin::Unknown => "unknown",
+            KeyOrigin::Solver => "solver",
+            KeyOrigin::Manual => "manual",
+            KeyOrigin::Guess => "guess",
+            KeyOrigin::Propagated => "propagated",
+        }
+    }
+
+    // Set by hand, by an accepted crib or pattern, or forced by the bytes
+    // around it, rather than voted or guessed for text
+    pub fn is_confirmed(self) -> bool {
+        matches!(self, KeyOrigin::Manual | KeyOrigin::Propagated)
+    }
+}
+
+// How a single plaintext byte should be presented
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PlainByte {
+    Unknown,
+    NonPrintable(u8),
+    OutsideAlphabet(u8),
+    Manual(u8),
+    Guessed(u8),
+    Valid(u8),
+}
+
+// Classify the plaintext byte obtained by decrypting 'cipher' with 'key'.
+// Invalid bytes take precedence over the key origin so that a wrong guess stands out.
+pub fn classify(cipher: u8, key: Option<u8>, origin: KeyOrigin, alphabet: Alphabet) -> PlainByte {
+    let c = match key {
+        Some(key_byte) => cipher ^ key_byte,
+        None => return PlainByte::Unknown,
+    };
+    if c != b' ' && !c.is_ascii_graphic() {
+        PlainByte::NonPrintable(c)
+    } else if !alphabet.contains(c) {
+        PlainByte::OutsideAlphabet(c)
+    } else {
+        match origin {
+            KeyOrigin::Manual => PlainByte::Manual(c),
+            KeyOrigin::Guess => PlainByte::Guessed(c),
+            _ => PlainByte::Valid(c),
+        }
+    }
+}
+
+// The multi-byte UTF-8 character starting at column 'j' and its length in
+// bytes, if every byte of it is known
+pub fn utf8_char(cipher: &[u8], key: &[Option<u8>], j: usize) -> Option<(char, usize)> {
+    let lead = cipher[j] ^ key[j]?;
+    let len = match lead {
+        0xc2..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf4 => 4,
+        _ => return None,
+    };
+    let bytes: Vec<u8> = (j..j + len)
+        .map(|i| Some(cipher.get(i)? ^ (*key.get(i)?)?))
+        .collect::<Option<_>>()?;
+    let c = std::str::from_utf8(&bytes).ok()?.chars().next()?;
+    Some((c, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            classify(0x41, None, KeyOrigin::Solver, Alphabet::Text),
+            PlainByte::Unknown
+        );
+        assert_eq!(
+            classify(0x41, Some(0x41), KeyOrigin::Solver, Alphabet::Text),
+            PlainByte::NonPrintable(0x00)
+        );
+        assert_eq!(
+            classify(b'#', Some(0x00), KeyOrigin::Manual, Alphabet::Text),
+            PlainByte::OutsideAlphabet(b'#')
+        );
+        assert_eq!(
+            classify(b'#', Some(0x00), KeyOrigin::Manual, Alphabet::Printable),
+            PlainByte::Manual(b'#')
+        );
+        assert_eq!(
+            classify(b'e', Some(0x00), KeyOrigin::Guess, Alphabet::Letters),
+            PlainByte::Guessed(b'e')
+        );
+        assert_eq!(
+            classify(
+                b'a' ^ 0x20,
+                Some(0x20),
+                KeyOrigin::Solver,
+                Alphabet::Letters
+            ),
+            PlainByte::Valid(b'a')
+        );
+    }
+
+    #[test]
+    fn test_utf8_char() {
+        let plain = "a\u{e9}\u{4e16}!".as_bytes();
+        let key: Vec<Option<u8>> = (0..plain.len()).map(|i| Some(i as u8 * 7)).collect();
+        let cipher: Vec<u8> = plain
+            .iter()
+            .zip(&key)
+            .map(|(p, k)| p ^ k.unwrap())
+            .collect();
+        assert_eq!(utf8_char(&cipher, &key, 0), None);
+        assert_eq!(utf8_char(&cipher, &key, 1), Some(('\u{e9}', 2)));
+        assert_eq!(utf8_char(&cipher, &key, 2), None);
+        assert_eq!(utf8_char(&cipher, &key, 3), Some(('\u{4e16}', 3)));
+        let mut partial = key.clone();
+        partial[5] = None;
+        assert_eq!(utf8_char(&cipher, &partial, 3), None);
+    }
+}
@@ -0,0 +1,199 @@
+use std::{collections::BTreeSet, ops::Range};
+
+use crate::plaintext::Alphabet;
+
+// Whether 'cur' can come right after 'prev' (itself after 'before') in natural text:
+// a period is followed by a space, a space by a word, a lowercase letter by
+// anything but a capital, and a sentence starts with a capital
+fn may_follow(before: Option<u8>, prev: u8, cur: u8) -> bool {
+    match prev {
+        b'.' | b'!' | b'?' => b" \"')".contains(&cur),
+        b',' | b';' | b':' => cur == b' ' || cur == b'"' || cur.is_ascii_digit(),
+        b' ' => {
+            let sentence_start = before.is_some_and(|c| b".!?".contains(&c));
+            let lowercase_sentence = sentence_start && cur.is_ascii_lowercase();
+            !b" .,;:!?)".contains(&cur) && !lowercase_sentence
+        }
+        c if c.is_ascii_lowercase() => !cur.is_ascii_uppercase(),
+        _ => true,
+    }
+}
+
+// Plaintext of a message at a column, if the key byte there is known
+fn plain(msg: &[u8], key: &[Option<u8>], j: usize) -> Option<u8> {
+    match (msg.get(j), key.get(j).copied().flatten()) {
+        (Some(c), Some(k)) => Some(c ^ k),
+        _ => None,
+    }
+}
+
+// Key bytes for column 'j' under which every enabled message covering it
+// decrypts to an alphabet character consistent with its known neighbours
+pub fn column_candidates(
+    encrypted_messages: &[Vec<u8>],
+    enabled: &[bool],
+    key: &[Option<u8>],
+    alphabet: Alphabet,
+    j: usize,
+) -> Vec<u8> {
+    let covering: Vec<&Vec<u8>> = encrypted_messages
+        .iter()
+        .zip(enabled)
+        .filter(|(m, enabled)| **enabled && m.len() > j)
+        .map(|(m, _)| m)
+        .collect();
+    if covering.is_empty() {
+        return Vec::new();
+    }
+    (0..=255u8)
+        .filter(|k| {
+            covering.iter().all(|msg| {
+                let x = msg[j] ^ k;
+                let p1 = j.checked_sub(1).and_then(|i| plain(msg, key, i));
+                let p2 = j.checked_sub(2).and_then(|i| plain(msg, key, i));
+                let n1 = plain(msg, key, j + 1);
+                let n2 = plain(msg, key, j + 2);
+                alphabet.contains(x)
+                    && (j > 0 || !x.is_ascii_lowercase())
+                    && p1.is_none_or(|p1| may_follow(p2, p1, x))
+                    && n1.is_none_or(|n1| may_follow(p1, x, n1))
+                    && match (n1, n2) {
+                        (Some(n1), Some(n2)) => may_follow(Some(x), n1, n2),
+                        _ => true,
+                    }
+            })
+        })
+        .collect()
+}
+
+// Columns whose candidates depend on the key byte of column 'j'
+pub fn neighbours(j: usize, len: usize) -> Range<usize> {
+    j.saturating_sub(2)..(j + 3).min(len)
+}
+
+// Narrow every unknown column to its candidate key bytes, fixing the columns
+// left with a single candidate until nothing changes. Returns the candidates of
+// each column still unknown and the columns that were fixed.
+pub fn propagate(
+    encrypted_messages: &[Vec<u8>],
+    enabled: &[bool],
+    key: &mut [Option<u8>],
+    alphabet: Alphabet,
+) -> (Vec<Vec<u8>>, Vec<usize>) {
+    let mut candidates = vec![Vec::new(); key.len()];
+    let fixed = propagate_columns(
+        encrypted_messages,
+        enabled,
+        key,
+        alphabet,
+        &mut candidates,
+        0..key.len(),
+    );
+    (candidates, fixed)
+}
+
+// Like 'propagate', but only narrow 'columns' again, along with the
+// neighbours of every column fixed on the way. The candidates of the other
+// columns are kept. Returns the columns that were fixed.
+pub fn propagate_columns(
+    encrypted_messages: &[Vec<u8>],
+    enabled: &[bool],
+    key: &mut [Option<u8>],
+    alphabet: Alphabet,
+    candidates: &mut [Vec<u8>],
+    columns: impl IntoIterator<Item = usize>,
+) -> Vec<usize> {
+    let mut pending: BTreeSet<usize> = columns.into_iter().filter(|j| *j < key.len()).collect();
+    let mut fixed = Vec::new();
+    while let Some(j) = pending.pop_first() {
+        if key[j].is_some() {
+            candidates[j].clear();
+            continue;
+        }
+        candidates[j] = column_candidates(encrypted_messages, enabled, key, alphabet, j);
+        if candidates[j].len() == 1 {
+            key[j] = Some(candidates[j][0]);
+            candidates[j].clear();
+            fixed.push(j);
+            pending.extend(neighbours(j, key.len()));
+        }
+    }
+    fixed
+}
+
+// Unknown columns covered by an enabled message where no key byte fits: the
+// known bytes around them contradict each other
+pub fn contradictions(
+    encrypted_messages: &[Vec<u8>],
+    enabled: &[bool],
+    key: &[Option<u8>],
+    candidates: &[Vec<u8>],
+) -> Vec<usize> {
+    (0..key.len())
+        .filter(|j| key[*j].is_none() && candidates.get(*j).is_some_and(|c| c.is_empty()))
+        .filter(|j| {
+            encrypted_messages
+                .iter()
+                .zip(enabled)
+                .any(|(m, enabled)| *enabled && m.len() > *j)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decryption::xor;
+
+    #[test]
+    fn test_propagate() {
+        let true_key = [0x3a, 0x91, 0x07, 0xc4, 0x5e, 0x22, 0x68];
+        let plaintexts: [&[u8]; 3] = [b"Hi. Yes", b"So, no.", b"Ok. Go "];
+        let messages: Vec<Vec<u8>> = plaintexts.iter().map(|p| xor(p, &true_key)).collect();
+        let mut key = true_key.map(Some);
+        key[3] = None;
+        let (candidates, fixed) = propagate(&messages, &[true; 3], &mut key, Alphabet::Letters);
+        assert_eq!(fixed, vec![3]);
+        assert_eq!(key[3], Some(true_key[3]));
+        assert!(candidates.iter().all(|c| c.is_empty()));
+
+        // A quote may also follow a period, so the text alphabet leaves two options
+        key[3] = None;
+        let (candidates, fixed) = propagate(&messages, &[true; 3], &mut key, Alphabet::Text);
+        assert!(fixed.is_empty());
+        assert_eq!(candidates[3].len(), 2);
+        assert!(candidates[3].contains(&true_key[3]));
+
+        // Narrowing only the columns around an edit agrees with a full pass
+        let mut candidates = candidates;
+        key[3] = Some(true_key[3]);
+        key[5] = None;
+        let mut full_key = key;
+        let (full_candidates, full_fixed) =
+            propagate(&messages, &[true; 3], &mut full_key, Alphabet::Text);
+        let fixed = propagate_columns(
+            &messages,
+            &[true; 3],
+            &mut key,
+            Alphabet::Text,
+            &mut candidates,
+            neighbours(5, 7),
+        );
+        assert_eq!(
+            (fixed, key, candidates),
+            (full_fixed, full_key, full_candidates)
+        );
+
+        // A space must follow "Hi." but cannot come before a period
+        let mut messages = messages;
+        messages[0][4] = b'.' ^ true_key[4];
+        let mut key = true_key.map(Some);
+        key[3] = None;
+        let (candidates, _) = propagate(&messages, &[true; 3], &mut key, Alphabet::Letters);
+        assert_eq!(
+            contradictions(&messages, &[true; 3], &key, &candidates),
+            vec![3]
+        );
+        assert!(contradictions(&messages, &[false; 3], &key, &candidates).is_empty());
+    }
+}
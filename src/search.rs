@@ -0,0 +1,244 @@
+use regex::Regex;
+use regex_syntax::hir::{Class, ClassUnicode, ClassUnicodeRange, Hir, HirKind};
+
+use crate::plaintext::{utf8_char, Alphabet};
+
+// Stands for an unknown byte in the searched text; every character of a search
+// pattern also matches it
+const WILDCARD: char = '\u{fffd}';
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SearchMode {
+    // Regex over the recovered plaintexts
+    Regex,
+    // Places where a literal text could be, given the known bytes
+    Fit,
+}
+
+impl SearchMode {
+    pub fn next(self) -> SearchMode {
+        match self {
+            SearchMode::Regex => SearchMode::Fit,
+            SearchMode::Fit => SearchMode::Regex,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SearchMode::Regex => "regex",
+            SearchMode::Fit => "fit",
+        }
+    }
+}
+
+// Columns [start, end) of a message matched by a search
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SearchMatch {
+    pub message: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+// Let every character the pattern matches also match the wildcard
+fn with_wildcards(hir: Hir) -> Hir {
+    let wildcard = ClassUnicodeRange::new(WILDCARD, WILDCARD);
+    match hir.into_kind() {
+        HirKind::Literal(literal) => {
+            let text = String::from_utf8_lossy(&literal.0).into_owned();
+            Hir::concat(
+                text.chars()
+                    .map(|c| {
+                        let class = ClassUnicode::new([ClassUnicodeRange::new(c, c), wildcard]);
+                        Hir::class(Class::Unicode(class))
+                    })
+                    .collect(),
+            )
+        }
+        HirKind::Class(Class::Unicode(mut class)) => {
+            class.push(wildcard);
+            Hir::class(Class::Unicode(class))
+        }
+        HirKind::Repetition(mut repetition) => {
+            repetition.sub = Box::new(with_wildcards(*repetition.sub));
+            Hir::repetition(repetition)
+        }
+        HirKind::Capture(mut capture) => {
+            capture.sub = Box::new(with_wildcards(*capture.sub));
+            Hir::capture(capture)
+        }
+        HirKind::Concat(subs) => Hir::concat(subs.into_iter().map(with_wildcards).collect()),
+        HirKind::Alternation(subs) => {
+            Hir::alternation(subs.into_iter().map(with_wildcards).collect())
+        }
+        HirKind::Class(class) => Hir::class(class),
+        HirKind::Look(look) => Hir::look(look),
+        HirKind::Empty => Hir::empty(),
+    }
+}
+
+pub fn compile(pattern: &str) -> Result<Regex, String> {
+    let hir = regex_syntax::parse(pattern).map_err(|err| err.to_string())?;
+    Regex::new(&with_wildcards(hir).to_string()).map_err(|err| err.to_string())
+}
+
+// Plaintext of a message with unknown bytes replaced by the wildcard, and the
+// column each byte offset of it comes from. A multi-byte UTF-8 character whose
+// bytes are all known is decoded; other bytes are read as Latin-1.
+fn render(msg: &[u8], key: &[Option<u8>]) -> (String, Vec<usize>) {
+    let len = msg.len().min(key.len());
+    let mut text = String::new();
+    let mut columns = Vec::new();
+    let mut j = 0;
+    while j < len {
+        let (c, width) = match (utf8_char(msg, key, j), key[j]) {
+            (Some(decoded), _) => decoded,
+            (None, Some(k)) => ((msg[j] ^ k) as char, 1),
+            (None, None) => (WILDCARD, 1),
+        };
+        text.push(c);
+        columns.resize(text.len(), j);
+        j += width;
+    }
+    columns.push(len);
+    (text, columns)
+}
+
+// Matches of 'regex' in the plaintexts. Matches made mostly of unknown bytes
+// are dropped, as they say little; the fit search is meant for those places.
+pub fn find_matches(
+    regex: &Regex,
+    encrypted_messages: &[Vec<u8>],
+    key: &[Option<u8>],
+) -> Vec<SearchMatch> {
+    let mut matches = Vec::new();
+    for (i, msg) in encrypted_messages.iter().enumerate() {
+        let (text, columns) = render(msg, key);
+        for m in regex.find_iter(&text) {
+            let known = m.as_str().chars().filter(|c| *c != WILDCARD).count();
+            if known > 0 && 2 * known >= m.as_str().chars().count() {
+                matches.push(SearchMatch {
+                    message: i,
+                    start: columns[m.start()],
+                    end: columns[m.end()],
+                });
+            }
+        }
+    }
+    matches
+}
+
+// Places where 'pattern' could be in a message: it agrees with every known
+// byte, and the key bytes it implies for the unknown columns decrypt the other
+// enabled messages into the alphabet
+pub fn find_fits(
+    pattern: &[u8],
+    encrypted_messages: &[Vec<u8>],
+    enabled: &[bool],
+    key: &[Option<u8>],
+    alphabet: Alphabet,
+) -> Vec<SearchMatch> {
+    let mut fits = Vec::new();
+    if pattern.is_empty() {
+        return fits;
+    }
+    for (i, msg) in encrypted_messages.iter().enumerate() {
+        for start in 0..(msg.len() + 1).saturating_sub(pattern.len()) {
+            let fits_here = pattern.iter().enumerate().all(|(n, p)| {
+                let j = start + n;
+                match key[j] {
+                    Some(k) => msg[j] ^ k == *p,
+                    None => {
+                        let k = msg[j] ^ p;
+                        encrypted_messages
+                            .iter()
+                            .zip(enabled)
+                            .enumerate()
+                            .filter(|(other, (m, enabled))| *other != i && **enabled && m.len() > j)
+                            .all(|(_, (m, _))| alphabet.contains(m[j] ^ k))
+                    }
+                }
+            });
+            if fits_here {
+                fits.push(SearchMatch {
+                    message: i,
+                    start,
+                    end: start + pattern.len(),
+                });
+            }
+        }
+    }
+    fits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decryption::xor;
+
+    #[test]
+    fn test_search() {
+        let true_key = [0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88];
+        let messages: Vec<Vec<u8>> = [b"flag{ab}".as_slice(), b"the flag", b"a flag!!"]
+            .iter()
+            .map(|p| xor(p, &true_key))
+            .collect();
+        let mut key = true_key.map(Some);
+        key[2] = None;
+
+        let regex = compile("fl[a-z]g").unwrap();
+        let found = find_matches(&regex, &messages, &key);
+        assert_eq!(
+            found,
+            vec![
+                SearchMatch {
+                    message: 0,
+                    start: 0,
+                    end: 4
+                },
+                SearchMatch {
+                    message: 1,
+                    start: 4,
+                    end: 8
+                },
+                SearchMatch {
+                    message: 2,
+                    start: 2,
+                    end: 6
+                },
+            ]
+        );
+        assert!(compile("fl(ag").is_err());
+
+        key[0] = None;
+        key[1] = None;
+        let fits = find_fits(b"the", &messages, &[true; 3], &key, Alphabet::Letters);
+        assert!(fits.contains(&SearchMatch {
+            message: 1,
+            start: 0,
+            end: 3
+        }));
+        // "the" at the start of message 0 would put a '$' in message 2
+        assert!(!fits.iter().any(|m| m.message == 0 && m.start == 0));
+
+        // A decoded UTF-8 character matches as one character over its columns
+        let plaintext = "caf\u{e9} \u{e9}t\u{e9}".as_bytes();
+        let messages = vec![xor(plaintext, &true_key.repeat(2))];
+        let mut key: Vec<Option<u8>> = true_key.repeat(2).into_iter().map(Some).collect();
+        let regex = compile("\u{e9}t.$").unwrap();
+        let found = find_matches(&regex, &messages, &key);
+        assert_eq!(
+            found,
+            vec![SearchMatch {
+                message: 0,
+                start: 6,
+                end: 11
+            }]
+        );
+        // With its first byte unknown, a character is a wildcard and a stray
+        // byte
+        key[9] = None;
+        assert!(find_matches(&regex, &messages, &key).is_empty());
+        let found = find_matches(&compile("\u{e9}t..$").unwrap(), &messages, &key);
+        assert_eq!(found[0].end, 11);
+    }
+}
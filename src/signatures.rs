@@ -0,0 +1,269 @@
+use crate::{decryption::key_from_plaintext, hunt::Crib};
+
+// Fraction of a message's bytes that must be known before it is written out
+pub const MIN_FILE_COVERAGE: f64 = 0.9;
+
+// Known bytes needed to place a field away from its usual offset
+const MIN_CONFIRMED: usize = 2;
+
+// Where a fixed field sits in a file
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Anchor {
+    // At this offset from the start
+    Start(usize),
+    // This many bytes before the end
+    End(usize),
+    // Anywhere, such as the header of a member inside an archive
+    Anywhere,
+}
+
+pub struct Field {
+    pub name: &'static str,
+    pub anchor: Anchor,
+    pub bytes: &'static [u8],
+}
+
+// Magic numbers and fixed chunk fields of a file format
+pub struct Signature {
+    pub name: &'static str,
+    pub extension: &'static str,
+    pub fields: &'static [Field],
+}
+
+const fn field(name: &'static str, anchor: Anchor, bytes: &'static [u8]) -> Field {
+    Field {
+        name,
+        anchor,
+        bytes,
+    }
+}
+
+pub const SIGNATURES: &[Signature] = &[
+    Signature {
+        name: "PNG",
+        extension: "png",
+        fields: &[
+            field("magic", Anchor::Start(0), b"\x89PNG\r\n\x1a\n"),
+            field("IHDR chunk", Anchor::Start(8), b"\0\0\0\x0dIHDR"),
+            field("IDAT chunk", Anchor::Anywhere, b"IDAT"),
+            field(
+                "IEND chunk",
+                Anchor::End(12),
+                b"\0\0\0\0IEND\xae\x42\x60\x82",
+            ),
+        ],
+    },
+    Signature {
+        name: "ZIP",
+        extension: "zip",
+        fields: &[
+            field("local file header", Anchor::Start(0), b"PK\x03\x04"),
+            field("local file header", Anchor::Anywhere, b"PK\x03\x04"),
+            field("central directory header", Anchor::Anywhere, b"PK\x01\x02"),
+            field(
+                "end of central directory",
+                Anchor::End(22),
+                b"PK\x05\x06\0\0\0\0",
+            ),
+        ],
+    },
+    Signature {
+        name: "gzip",
+        extension: "gz",
+        fields: &[field("magic", Anchor::Start(0), b"\x1f\x8b\x08")],
+    },
+    Signature {
+        name: "PDF",
+        extension: "pdf",
+        fields: &[
+            field("header", Anchor::Start(0), b"%PDF-1."),
+            field("object", Anchor::Anywhere, b" 0 obj"),
+            field("stream", Anchor::Anywhere, b"endstream"),
+        ],
+    },
+    Signature {
+        name: "JPEG",
+        extension: "jpg",
+        fields: &[
+            field("start of image", Anchor::Start(0), b"\xff\xd8\xff"),
+            field("end of image", Anchor::End(2), b"\xff\xd9"),
+        ],
+    },
+    Signature {
+        name: "GIF",
+        extension: "gif",
+        fields: &[
+            field("header", Anchor::Start(0), b"GIF89a"),
+            field("trailer", Anchor::End(1), b"\x3b"),
+        ],
+    },
+];
+
+// Known key bytes a placement agrees with, or None if it contradicts one
+fn confirmed(msg: &[u8], key: &[Option<u8>], bytes: &[u8], offset: usize) -> Option<usize> {
+    let mut count = 0;
+    for (n, byte) in bytes.iter().enumerate() {
+        if let Some(k) = key[offset + n] {
+            if msg[offset + n] ^ k != *byte {
+                return None;
+            }
+            count += 1;
+        }
+    }
+    Some(count)
+}
+
+// Offsets 'field' may be placed at in a message of 'len' bytes: its anchored
+// offset, or for floating fields every offset
+fn offsets(field: &Field, len: usize) -> Vec<usize> {
+    let last = match len.checked_sub(field.bytes.len()) {
+        Some(last) => last,
+        None => return Vec::new(),
+    };
+    match field.anchor {
+        Anchor::Start(offset) if offset <= last => vec![offset],
+        Anchor::End(back) if back >= field.bytes.len() && back <= len => vec![len - back],
+        Anchor::Anywhere => (0..=last).collect(),
+        _ => Vec::new(),
+    }
+}
+
+// Placements of the signature fields that agree with the known key and add
+// key bytes. Anchored fields are always offered; floating ones only where
+// enough known bytes confirm them. The more confirmed bytes, the better.
+pub fn signature_cribs(
+    encrypted_messages: &[Vec<u8>],
+    enabled: &[bool],
+    key: &[Option<u8>],
+) -> Vec<Crib> {
+    let mut cribs = Vec::new();
+    for (i, msg) in encrypted_messages.iter().enumerate() {
+        if !enabled[i] {
+            continue;
+        }
+        for signature in SIGNATURES {
+            for field in signature.fields {
+                for offset in offsets(field, msg.len()) {
+                    let count = match confirmed(msg, key, field.bytes, offset) {
+                        Some(count) => count,
+                        None => continue,
+                    };
+                    if field.anchor == Anchor::Anywhere && count < MIN_CONFIRMED {
+                        continue;
+                    }
+                    let new_key: Vec<(usize, u8)> = key_from_plaintext(msg, field.bytes, offset)
+                        .into_iter()
+                        .filter(|(pos, _)| key[*pos].is_none())
+                        .collect();
+                    if new_key.is_empty() {
+                        continue;
+                    }
+                    cribs.push(Crib {
+                        message: i,
+                        offset,
+                        label: format!("{} {}", signature.name, field.name),
+                        key: new_key,
+                        score: 0.0,
+                        confirmed: count,
+                    });
+                }
+            }
+        }
+    }
+    cribs.sort_by_key(|c| std::cmp::Reverse(c.confirmed));
+    cribs
+}
+
+// File extension for a plaintext, from the magic number it starts with
+pub fn extension(plaintext: &[u8]) -> &'static str {
+    SIGNATURES
+        .iter()
+        .find(|signature| {
+            signature
+                .fields
+                .iter()
+                .filter(|field| field.anchor == Anchor::Start(0))
+                .any(|field| plaintext.starts_with(field.bytes))
+        })
+        .map_or("bin", |signature| signature.extension)
+}
+
+// A plaintext known well enough to be written out as a file
+pub struct PlaintextFile {
+    pub message: usize,
+    pub extension: &'static str,
+    pub plaintext: Vec<u8>,
+    // Bytes under an unknown key byte, written as zeros
+    pub unknown: usize,
+}
+
+// The plaintexts known well enough to be written out as files, counting
+// only the bytes of 'key'
+pub fn plaintext_files(encrypted_messages: &[Vec<u8>], key: &[Option<u8>]) -> Vec<PlaintextFile> {
+    let mut files = Vec::new();
+    for (i, msg) in encrypted_messages.iter().enumerate() {
+        let known = msg.iter().zip(key).filter(|(_, k)| k.is_some()).count();
+        if msg.is_empty() || (known as f64) < MIN_FILE_COVERAGE * msg.len() as f64 {
+            continue;
+        }
+        let plaintext: Vec<u8> = msg
+            .iter()
+            .zip(key)
+            .map(|(c, k)| k.map_or(0, |k| c ^ k))
+            .collect();
+        files.push(PlaintextFile {
+            message: i,
+            extension: extension(&plaintext),
+            plaintext,
+            unknown: msg.len() - known,
+        });
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decryption::xor;
+
+    #[test]
+    fn test_signature_cribs() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(b"\0\0\0\x10\0\0\0\x10\x08\x02\0\0\0\x90\x91\x68\x36");
+        png.extend_from_slice(b"\0\0\0\0IEND\xae\x42\x60\x82");
+        let mut zip = b"PK\x03\x04\x14\0\0\0\0\0".to_vec();
+        zip.extend_from_slice(b"abcdefghPK\x01\x02\x14\0\x14\0\0\0\0\0");
+        zip.resize(png.len(), b'.');
+        let true_key: Vec<u8> = (0..png.len()).map(|i| (i * 73 + 5) as u8).collect();
+        let messages = vec![xor(&png, &true_key), xor(&zip, &true_key)];
+        let mut key = vec![None; true_key.len()];
+
+        // With nothing known only anchored fields are offered
+        let cribs = signature_cribs(&messages, &[true; 2], &key);
+        assert!(cribs.iter().all(|c| c.confirmed == 0));
+        let magic = cribs
+            .iter()
+            .find(|c| c.message == 0 && c.label == "PNG magic")
+            .unwrap();
+        assert!(magic.key.iter().all(|(pos, byte)| true_key[*pos] == *byte));
+
+        // Known bytes reveal the central directory header inside the ZIP
+        for pos in 18..21 {
+            key[pos] = Some(true_key[pos]);
+        }
+        let cribs = signature_cribs(&messages, &[true; 2], &key);
+        assert_eq!(
+            (cribs[0].message, cribs[0].offset, cribs[0].label.as_str()),
+            (1, 18, "ZIP central directory header")
+        );
+
+        assert_eq!(extension(&png), "png");
+        assert_eq!(extension(b"plain"), "bin");
+        key = true_key.iter().copied().map(Some).collect();
+        key[25] = None;
+        let files = plaintext_files(&messages, &key);
+        assert_eq!(files.len(), 2);
+        assert_eq!((files[1].message, files[1].extension), (1, "zip"));
+        assert_eq!((files[0].plaintext[25], files[0].unknown), (0, 1));
+    }
+}
@@ -0,0 +1,122 @@
+use std::ops::Range;
+
+use crate::decryption::{ColumnVotes, UNANIMOUS};
+
+// Column vote tallies kept between edits, so that adding or removing a
+// ciphertext only solves the columns it covers again
+pub struct SolverState {
+    columns: Vec<ColumnVotes>,
+    key: Vec<Option<u8>>,
+    threshold: u32,
+}
+
+impl SolverState {
+    pub fn new() -> SolverState {
+        SolverState {
+            columns: Vec::new(),
+            key: Vec::new(),
+            threshold: UNANIMOUS,
+        }
+    }
+
+    // Percentage of the covering weight that must agree on a key byte
+    pub fn threshold(&self) -> u32 {
+        self.threshold
+    }
+
+    // Change the agreement threshold and return the columns solved again
+    pub fn set_threshold(&mut self, threshold: u32) -> Range<usize> {
+        self.threshold = threshold;
+        self.solve(0..self.columns.len())
+    }
+
+    pub fn key(&self) -> &[Option<u8>] {
+        &self.key
+    }
+
+    pub fn column(&self, column: usize) -> Option<&ColumnVotes> {
+        self.columns.get(column)
+    }
+
+    // Number of enabled ciphertexts covering a column
+    pub fn coverage(&self, column: usize) -> usize {
+        self.columns.get(column).map_or(0, |c| c.coverage)
+    }
+
+    // Count a ciphertext in with the given weight and return the columns whose
+    // key may have changed
+    pub fn add(&mut self, ciphertext: &[u8], weight: u32) -> Range<usize> {
+        while self.columns.len() < ciphertext.len() {
+            self.columns.push(ColumnVotes::new(None));
+            self.key.push(None);
+        }
+        for (column, byte) in self.columns.iter_mut().zip(ciphertext) {
+            column.add(*byte, weight);
+        }
+        self.solve(0..ciphertext.len())
+    }
+
+    // Count a ciphertext out with the weight it was added with and return the
+    // columns whose key may have changed
+    pub fn remove(&mut self, ciphertext: &[u8], weight: u32) -> Range<usize> {
+        for (column, byte) in self.columns.iter_mut().zip(ciphertext) {
+            column.remove(*byte, weight);
+        }
+        while self
+            .columns
+            .last()
+            .is_some_and(|column| column.coverage == 0)
+        {
+            self.columns.pop();
+            self.key.pop();
+        }
+        self.solve(0..ciphertext.len().min(self.columns.len()))
+    }
+
+    fn solve(&mut self, columns: Range<usize>) -> Range<usize> {
+        for j in columns.clone() {
+            self.key[j] = self.columns[j].key(self.threshold);
+        }
+        columns
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        challenge::{generate, ChallengeConfig, LengthDistribution, SAMPLE_CORPUS},
+        decryption::{decrypt_key, FULL_WEIGHT},
+        plaintext::Alphabet,
+    };
+
+    #[test]
+    fn test_incremental_matches_batch() {
+        let config = ChallengeConfig {
+            messages: 10,
+            lengths: LengthDistribution::Uniform(30, 70),
+            alphabet: Alphabet::Text,
+            seed: 3,
+        };
+        let challenge = generate(SAMPLE_CORPUS, &config).unwrap();
+        let mut state = SolverState::new();
+        for ciphertext in challenge.ciphertexts.iter() {
+            state.add(ciphertext, FULL_WEIGHT);
+        }
+        assert_eq!(state.key(), decrypt_key(&challenge.ciphertexts));
+
+        let longest = challenge
+            .ciphertexts
+            .iter()
+            .max_by_key(|c| c.len())
+            .unwrap();
+        state.remove(longest, FULL_WEIGHT);
+        let rest: Vec<Vec<u8>> = challenge
+            .ciphertexts
+            .iter()
+            .filter(|c| *c != longest)
+            .cloned()
+            .collect();
+        assert_eq!(state.key(), decrypt_key(&rest));
+    }
+}
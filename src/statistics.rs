@@ -0,0 +1,68 @@
+use crate::{decryption::ColumnVotes, language::LanguageModel};
+
+// What the solver saw in one key column
+pub struct ColumnStats {
+    pub coverage: usize,
+    pub best_votes: u32,
+    // Key bytes implied by the most voted ciphertext bytes, with their votes
+    pub candidates: Vec<(u8, u32)>,
+    // Average bits per character of the column's plaintext under the language
+    // model, if the key byte is known. Lower means more text-like.
+    pub entropy: Option<f64>,
+}
+
+pub fn column_stats(
+    votes: Option<&ColumnVotes>,
+    column: &[u8],
+    key: Option<u8>,
+    model: &LanguageModel,
+) -> ColumnStats {
+    let mut candidates: Vec<(u8, u32)> = match votes {
+        Some(votes) if votes.coverage > 1 => (0..=255u8)
+            .filter(|byte| votes.counts[*byte as usize] > 0)
+            .map(|byte| (byte ^ b' ', votes.votes(byte)))
+            .collect(),
+        _ => Vec::new(),
+    };
+    candidates.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    candidates.truncate(5);
+
+    let entropy = match key {
+        Some(k) if !column.is_empty() => Some(
+            column
+                .iter()
+                .map(|c| -model.probability(&[], c ^ k).log2())
+                .sum::<f64>()
+                / column.len() as f64,
+        ),
+        _ => None,
+    };
+
+    ColumnStats {
+        coverage: votes.map_or(0, |v| v.coverage),
+        best_votes: candidates.first().map_or(0, |c| c.1),
+        candidates,
+        entropy,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::challenge::SAMPLE_CORPUS;
+
+    #[test]
+    fn test_column_stats() {
+        let model = LanguageModel::new(SAMPLE_CORPUS);
+        let key = 0x5a;
+        let column: Vec<u8> = b" eta".iter().map(|c| c ^ key).collect();
+        let votes = ColumnVotes::new(column.iter().copied());
+        let stats = column_stats(Some(&votes), &column, Some(key), &model);
+        assert_eq!(stats.coverage, 4);
+        assert_eq!(stats.best_votes, 3);
+        assert_eq!(stats.candidates[0], (key, 3));
+
+        let wrong = column_stats(Some(&votes), &column, Some(key ^ 0x80), &model);
+        assert!(wrong.entropy.unwrap() > stats.entropy.unwrap());
+    }
+}
@@ -3,19 +3,36 @@ use tui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans, Text},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Tabs, Wrap},
+    widgets::{
+        BarChart, Block, Borders, Clear, List, ListItem, ListState, Paragraph, Sparkline, Tabs,
+        Wrap,
+    },
     Frame,
 };
-use unicode_width::UnicodeWidthStr;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-use crate::app_state::{App, InputMode, Tab};
+use crate::{
+    app_state::{App, ForgeField, InputMode, KeyOrigin, Level, Tab},
+    decryption::{classify_xor, get_space_indices, XorClass, FULL_WEIGHT},
+    keymap::{self, Action},
+    nonce::nonce_groups,
+    plaintext::{classify, utf8_char, Alphabet, PlainByte},
+    util::encode_hex,
+};
 
 pub fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     let size = f.size();
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(0)
-        .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+        .constraints(
+            [
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ]
+            .as_ref(),
+        )
         .split(size);
 
     let block = Block::default();
@@ -37,7 +54,195 @@ pub fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     match app.current_tab {
         Tab::Encrypted => draw_encrypted_messages_block(f, app, chunks[1]),
         Tab::Decryption => draw_decryption_block(f, app, chunks[1]),
+        Tab::Xor => draw_xor_block(f, app, chunks[1]),
+        Tab::Statistics => draw_statistics_block(f, app, chunks[1]),
+        Tab::Forge => draw_forge_block(f, app, chunks[1]),
     };
+    draw_status_line(f, app, chunks[2]);
+    if app.show_help {
+        draw_help(f, app, size);
+    }
+    if app.show_notifications {
+        draw_notifications(f, app, size);
+    }
+}
+
+fn level_style(level: Level) -> Style {
+    match level {
+        Level::Info => Style::default(),
+        Level::Error => Style::default().fg(Color::Red),
+    }
+}
+
+// The latest notification, with a hint for the history
+fn draw_status_line<B>(f: &mut Frame<B>, app: &App, area: Rect)
+where
+    B: Backend,
+{
+    let mut spans = match app.notifications.front() {
+        Some(notification) => vec![Span::styled(
+            notification.text.clone(),
+            level_style(notification.level),
+        )],
+        None => vec![Span::raw(app.key_summary())],
+    };
+    if app.notifications.len() > 1 {
+        spans.push(Span::styled(
+            format!(
+                "  (+{} earlier, m for history)",
+                app.notifications.len() - 1
+            ),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    f.render_widget(Paragraph::new(Spans::from(spans)), area);
+}
+
+// One-line summary of the keys of the current tab and mode, built from the
+// bindings so it never disagrees with them. Help comes first as the line is
+// cut on narrow terminals; keys sharing an action are listed together.
+fn hint_spans(app: &App) -> Vec<Span<'static>> {
+    let mut bindings = keymap::bindings(&app.current_tab, &app.input_mode);
+    bindings.sort_by_key(|b| b.action != Action::Help);
+    let mut groups: Vec<(Action, Vec<String>, &'static str)> = Vec::new();
+    for binding in bindings {
+        match groups.iter_mut().find(|g| g.0 == binding.action) {
+            Some(group) => group.1.push(keymap::key_names(binding)),
+            None => groups.push((
+                binding.action,
+                vec![keymap::key_names(binding)],
+                binding.help,
+            )),
+        }
+    }
+    let mut spans = Vec::new();
+    for (i, (action, keys, help)) in groups.into_iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::raw(", "));
+        }
+        // Typed text has no key to name
+        if action != Action::Type {
+            spans.push(Span::styled(
+                keys.join("/"),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            spans.push(Span::raw(" to "));
+        }
+        spans.push(Span::raw(help));
+    }
+    spans
+}
+
+// Centered popup 'height' rows high, clamped to 'area'
+fn popup_area(area: Rect, height: u16) -> Rect {
+    let height = height.min(area.height);
+    let width = (area.width * 3 / 4).max(40).min(area.width);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+// Strip along the bottom of 'area' for a list of 'rows' items: at most half
+// of it, but at least one row with its borders when it fits
+fn list_popup_area(area: Rect, rows: usize) -> Rect {
+    let height = (rows + 2)
+        .min(area.height as usize / 2)
+        .max(3)
+        .min(area.height as usize) as u16;
+    Rect {
+        y: area.y + area.height - height,
+        height,
+        ..area
+    }
+}
+
+// Popup with the recent notifications, newest first
+fn draw_notifications<B>(f: &mut Frame<B>, app: &App, area: Rect)
+where
+    B: Backend,
+{
+    let mut lines: Vec<Spans> = app
+        .notifications
+        .iter()
+        .map(|n| Spans::from(Span::styled(n.text.clone(), level_style(n.level))))
+        .collect();
+    if lines.is_empty() {
+        lines.push(Spans::from("No notifications yet"));
+    }
+    lines.push(Spans::from(""));
+    lines.push(Spans::from(Span::styled(
+        "Press any key to close",
+        Style::default().fg(Color::DarkGray),
+    )));
+    let popup = popup_area(area, lines.len() as u16 + 2);
+    let history = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Notifications"),
+        )
+        .wrap(Wrap { trim: false });
+    f.render_widget(Clear, popup);
+    f.render_widget(history, popup);
+}
+
+// Popup listing the bindings of the current tab and mode, taken from the
+// same table the key handling uses
+fn draw_help<B>(f: &mut Frame<B>, app: &App, area: Rect)
+where
+    B: Backend,
+{
+    let bindings = keymap::bindings(&app.current_tab, &app.input_mode);
+    let names: Vec<String> = bindings.iter().map(|b| keymap::key_names(b)).collect();
+    let width = names.iter().map(|n| n.width()).max().unwrap_or(0);
+    let mut lines: Vec<Spans> = names
+        .iter()
+        .zip(&bindings)
+        .map(|(name, binding)| {
+            Spans::from(vec![
+                Span::styled(
+                    format!("{:>width$}", name, width = width),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw("  "),
+                Span::raw(binding.help),
+            ])
+        })
+        .collect();
+    lines.push(Spans::from(""));
+    lines.push(Spans::from(Span::styled(
+        "Press any key to close",
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    let popup = popup_area(area, lines.len() as u16 + 2);
+    let title = format!(
+        "Keys: {} tab, {} mode",
+        app.titles[app.get_current_tab_index()],
+        mode_name(&app.input_mode)
+    );
+    let help = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(Clear, popup);
+    f.render_widget(help, popup);
+}
+
+fn mode_name(mode: &InputMode) -> &'static str {
+    match mode {
+        InputMode::Normal => "normal",
+        InputMode::Editing => "editing",
+        InputMode::KnownPlaintext => "known plaintext",
+        InputMode::Export => "export",
+        InputMode::Search => "search",
+        InputMode::Hunt => "flag hunting",
+        InputMode::BulkPaste => "bulk paste",
+        InputMode::Overwrite => "overwrite",
+        InputMode::Signatures => "file signatures",
+        InputMode::Verify => "keystream check",
+        InputMode::Patterns => "key patterns",
+    }
 }
 
 fn draw_encrypted_messages_block<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
@@ -56,49 +261,66 @@ where
         )
         .split(area);
 
-    let (msg, style) = match app.input_mode {
-        InputMode::Normal => (
-            vec![
-                Span::raw("Press "),
-                Span::styled("q", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to exit, "),
-                Span::styled("e", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to start editing."),
-            ],
-            Style::default().add_modifier(Modifier::RAPID_BLINK),
-        ),
-        InputMode::Editing => (
-            vec![
-                Span::raw("Press "),
-                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to stop editing, "),
-                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to record the message"),
-            ],
-            Style::default(),
-        ),
+    let style = match app.input_mode {
+        InputMode::Normal => Style::default().add_modifier(Modifier::RAPID_BLINK),
+        _ => Style::default(),
     };
-    let mut text = Text::from(Spans::from(msg));
+    let mut text = Text::from(Spans::from(hint_spans(app)));
     text.patch_style(style);
     let help_message = Paragraph::new(text);
     f.render_widget(help_message, chunks[0]);
 
-    let input = Paragraph::new(app.input.as_ref())
+    // Wrap the input ourselves so the cursor lands where the character is
+    let width = chunks[1].width.saturating_sub(2) as usize;
+    let height = chunks[1].height.saturating_sub(2) as usize;
+    let (rows, (row, column)) = app.add_input.wrap(width);
+    let scroll = (row + 1).saturating_sub(height);
+    // Mark the character that made the last submission invalid
+    let error_at = app.add_error.map(|i| (i / width.max(1), i % width.max(1)));
+    let rows: Vec<Spans> = rows
+        .into_iter()
+        .enumerate()
+        .map(|(r, text)| match error_at {
+            Some((error_row, error_column)) if error_row == r => Spans::from(
+                text.chars()
+                    .enumerate()
+                    .map(|(c, ch)| match c == error_column {
+                        true => Span::styled(
+                            ch.to_string(),
+                            Style::default().fg(Color::White).bg(Color::Red),
+                        ),
+                        false => Span::raw(ch.to_string()),
+                    })
+                    .collect::<Vec<Span>>(),
+            ),
+            _ => Spans::from(text),
+        })
+        .collect();
+    let input = Paragraph::new(rows)
         .style(match app.input_mode {
-            InputMode::Normal => Style::default(),
+            InputMode::Normal
+            | InputMode::KnownPlaintext
+            | InputMode::Export
+            | InputMode::Search
+            | InputMode::Hunt
+            | InputMode::BulkPaste
+            | InputMode::Overwrite
+            | InputMode::Signatures
+            | InputMode::Verify
+            | InputMode::Patterns => Style::default(),
             InputMode::Editing => Style::default().fg(Color::Green),
         })
-        .wrap(Wrap { trim: true })
+        .scroll((scroll as u16, 0))
         .block(Block::default().borders(Borders::ALL).title("Add"));
     f.render_widget(input, chunks[1]);
-    match app.input_mode {
-        InputMode::Normal => {}
-        InputMode::Editing => f.set_cursor(
-            chunks[1].x + 1 + app.position.1 as u16,
-            chunks[1].y + 1 + app.position.0 as u16,
-        ),
+    if let InputMode::Editing = app.input_mode {
+        f.set_cursor(
+            chunks[1].x + 1 + column as u16,
+            chunks[1].y + 1 + (row - scroll) as u16,
+        );
     }
 
+    let groups = nonce_groups(&app.nonces);
     let messages: Vec<ListItem> = app
         .encrypted_messages
         .iter()
@@ -106,15 +328,68 @@ where
         .map(|(i, m)| {
             let mut text = String::new();
             text.push_str(format!("{}:", i).as_str());
+            if app.message_weights[i] != FULL_WEIGHT {
+                text.push_str(&format!("({}%) ", app.message_weights[i]));
+            }
+            // Messages under the same nonce share a group number
+            if !app.nonces[i].is_empty() {
+                text.push_str(&format!("[#{} {}] ", groups[i], encode_hex(&app.nonces[i])));
+            }
             for byte in m {
                 text.push_str(format!("{:02X}", byte).as_str());
             }
-            ListItem::new(Spans::from(Span::raw(text)))
+            let mut style = Style::default();
+            if app.disabled_messages[i] {
+                style = style.fg(Color::DarkGray);
+            }
+            if matches!(app.input_mode, InputMode::Normal) && i == app.selected_message {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            ListItem::new(Spans::from(Span::styled(text, style)))
         })
         .collect();
-    let messages =
-        List::new(messages).block(Block::default().borders(Borders::ALL).title("Messages"));
+    let title = format!(
+        "Messages ({} nonces, new ciphertexts: {})",
+        groups.iter().max().map_or(0, |g| g + 1),
+        match app.nonce_len {
+            0 => "no nonce prefix".to_string(),
+            n => format!("{} byte nonce prefix", n),
+        }
+    );
+    let messages = List::new(messages).block(Block::default().borders(Borders::ALL).title(title));
     f.render_widget(messages, chunks[2]);
+
+    if let InputMode::BulkPaste = app.input_mode {
+        let paste = &app.bulk_paste;
+        let mut lines: Vec<ListItem> = paste
+            .accepted
+            .iter()
+            .map(|ciphertext| {
+                ListItem::new(Span::styled(
+                    format!(
+                        "added     {} bytes: {}",
+                        ciphertext.len(),
+                        encode_hex(ciphertext)
+                    ),
+                    Style::default().fg(Color::Green),
+                ))
+            })
+            .collect();
+        lines.extend(paste.rejected.iter().map(|(n, line, err)| {
+            ListItem::new(Span::styled(
+                format!("rejected  line {}: {} ({})", n, err, line),
+                Style::default().fg(Color::Red),
+            ))
+        }));
+        let title = format!(
+            "Paste: {} lines accepted, {} rejected",
+            paste.accepted.len(),
+            paste.rejected.len()
+        );
+        let summary = List::new(lines).block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(Clear, chunks[2]);
+        f.render_widget(summary, chunks[2]);
+    }
 }
 
 fn draw_decryption_block<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
@@ -134,64 +409,94 @@ where
         .split(area);
 
     let (msg, style) = match app.input_mode {
-        InputMode::Normal => match app.encrypted_messages.is_empty() {
-            false => (
-                vec![
-                    Span::raw("Press "),
-                    Span::styled("q", Style::default().add_modifier(Modifier::BOLD)),
-                    Span::raw(" to exit, "),
-                    Span::styled("e", Style::default().add_modifier(Modifier::BOLD)),
-                    Span::raw(" to start editing."),
-                ],
-                Style::default().add_modifier(Modifier::RAPID_BLINK),
-            ),
-            true => (
-                vec![Span::raw(
-                    "Add encrypted messages before attempting to decrypt.",
-                )],
-                Style::default().add_modifier(Modifier::RAPID_BLINK),
-            ),
-        },
-        InputMode::Editing => (
-            vec![
-                Span::raw("Press "),
-                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to stop editing, "),
-                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to record the message"),
-            ],
-            Style::default(),
+        InputMode::Normal if app.encrypted_messages.is_empty() => (
+            vec![Span::raw(
+                "Add encrypted messages before attempting to decrypt.",
+            )],
+            Style::default().add_modifier(Modifier::RAPID_BLINK),
+        ),
+        InputMode::Normal => (
+            hint_spans(app),
+            Style::default().add_modifier(Modifier::RAPID_BLINK),
         ),
+        _ => (hint_spans(app), Style::default()),
     };
     let mut text = Text::from(Spans::from(msg));
     text.patch_style(style);
     let help_message = Paragraph::new(text);
     f.render_widget(help_message, chunks[0]);
 
+    // Scroll the messages so the cursor row stays in view
+    let height = chunks[1].height.saturating_sub(2) as usize;
+    let row = app
+        .position
+        .0
+        .min(app.encrypted_messages.len().saturating_sub(1));
+    let scroll = (row + 1).saturating_sub(height);
+    let match_at = search_match_columns(app);
+    let (key, key_origin) = app.preview_key();
     let messages: Vec<ListItem> = app
         .encrypted_messages
         .iter()
         .enumerate()
+        .skip(scroll)
         .map(|(i, m)| {
-            let m_to_string: String = m
-                .iter()
-                .enumerate()
-                .map(|(j, byte)| {
-                    if let Some(key_char) = app.key[j] {
-                        let c = *byte ^ key_char;
-                        if c.is_ascii() {
-                            return c as char;
-                        }
+            let label_style = match app.input_mode {
+                InputMode::KnownPlaintext | InputMode::Overwrite if i == app.position.0 => {
+                    Style::default().add_modifier(Modifier::REVERSED)
+                }
+                _ if app.disabled_messages[i] => Style::default().fg(Color::DarkGray),
+                _ => Style::default(),
+            };
+            let mut spans = vec![Span::styled(format!("{}:", i), label_style), Span::raw(" ")];
+            let columns = match app.binary {
+                true => hex_spans(m, &key, &key_origin),
+                false => plain_spans(m, &key, &key_origin, app.alphabet),
+            };
+            spans.extend(columns.into_iter().enumerate().map(|(j, span)| {
+                // The column of a guess waiting for confirmation, or the span
+                // being overwritten
+                let (start, end) = app.overwrite_range;
+                let overwritten = matches!(app.input_mode, InputMode::Overwrite)
+                    && i == app.position.0
+                    && (start..end.max(start + app.input.len())).contains(&j);
+                if overwritten || app.pending_guess.iter().any(|(column, _)| *column == j) {
+                    return Span::styled(
+                        span.content,
+                        span.style
+                            .bg(Color::DarkGray)
+                            .add_modifier(Modifier::UNDERLINED),
+                    );
+                }
+                match match_at[i].get(j).copied().flatten() {
+                    Some(n) if n == app.search_index => {
+                        Span::styled(span.content, span.style.bg(Color::Magenta))
                     }
-                    '_'
-                })
-                .collect();
-            let content = vec![Spans::from(Span::raw(format!("{}: {}", i, m_to_string)))];
-            ListItem::new(content)
+                    Some(_) => Span::styled(span.content, span.style.bg(Color::Blue)),
+                    None => span,
+                }
+            }));
+            ListItem::new(Spans::from(spans))
         })
         .collect();
-    let messages =
-        List::new(messages).block(Block::default().borders(Borders::ALL).title("Messages"));
+    let mut title = format!(
+        "Messages (alphabet: {}, propagation: {}, agreement: {}%)",
+        app.alphabet.name(),
+        if app.propagate { "on" } else { "off" },
+        app.solver.threshold()
+    );
+    if !app.search_pattern.is_empty() {
+        title.push_str(&match app.search_matches.len() {
+            0 => format!(" /{}/: no match", app.search_pattern),
+            n => format!(
+                " /{}/: match {} of {}",
+                app.search_pattern,
+                app.search_index + 1,
+                n
+            ),
+        });
+    }
+    let messages = List::new(messages).block(Block::default().borders(Borders::ALL).title(title));
     f.render_widget(messages, chunks[1]);
 
     let key_string = app
@@ -205,17 +510,754 @@ where
         .join("");
     let input = Paragraph::new(key_string.as_ref())
         .style(match app.input_mode {
-            InputMode::Normal => Style::default(),
+            InputMode::Normal | InputMode::BulkPaste => Style::default(),
             InputMode::Editing => Style::default().fg(Color::Yellow),
+            InputMode::KnownPlaintext
+            | InputMode::Export
+            | InputMode::Search
+            | InputMode::Hunt
+            | InputMode::Overwrite
+            | InputMode::Signatures
+            | InputMode::Verify
+            | InputMode::Patterns => Style::default(),
         })
-        .block(Block::default().borders(Borders::ALL).title("Key"));
+        .block(Block::default().borders(Borders::ALL).title(key_title(app)));
     f.render_widget(input, chunks[2]);
 
+    if let InputMode::KnownPlaintext = app.input_mode {
+        let (readable, covered) = app.known_plaintext_report();
+        let title = format!(
+            "Known plaintext for message {} ({}/{} other messages readable)",
+            app.position.0, readable, covered
+        );
+        let input = Paragraph::new(app.input.as_ref())
+            .style(Style::default().fg(Color::Green))
+            .block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(Clear, chunks[2]);
+        f.render_widget(input, chunks[2]);
+    }
+
+    if let InputMode::Overwrite = app.input_mode {
+        let (start, end) = app.overwrite_range;
+        let end = end.max(start + app.input.len());
+        let (title, style) = match app.overwrite_key() {
+            Ok(key) => match app.overwrite_conflicts(&key).len() {
+                0 => (
+                    format!(
+                        "Overwrite message {} columns {}..{}",
+                        app.position.0, start, end
+                    ),
+                    Style::default().fg(Color::Green),
+                ),
+                n => (
+                    format!(
+                        "Overwrite message {} columns {}..{}: {} bytes of other messages leave the alphabet",
+                        app.position.0, start, end, n
+                    ),
+                    Style::default().fg(Color::Red),
+                ),
+            },
+            Err(err) => (
+                format!("Overwrite message {}: {}", app.position.0, err),
+                Style::default().fg(Color::Red),
+            ),
+        };
+        let input = Paragraph::new(app.input.as_ref())
+            .style(style)
+            .block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(Clear, chunks[2]);
+        f.render_widget(input, chunks[2]);
+    }
+
+    if let InputMode::Verify = app.input_mode {
+        let nonce = &app.nonces[app.position.0];
+        let title = format!(
+            "{} key for nonce {} (message {})",
+            app.stream_cipher.name(),
+            match nonce.is_empty() {
+                true => "(none)".to_string(),
+                false => encode_hex(nonce),
+            },
+            app.position.0
+        );
+        let input = Paragraph::new(app.input.as_ref())
+            .style(Style::default().fg(Color::Green))
+            .block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(Clear, chunks[2]);
+        f.render_widget(input, chunks[2]);
+    }
+
+    if let InputMode::Export = app.input_mode {
+        let what = match app.write_files {
+            true => "plaintext files".to_string(),
+            false => app.export_format.name().to_string(),
+        };
+        let (title, style) = match &app.export_error {
+            Some(err) => (
+                format!("Export {} failed: {}", what, err),
+                Style::default().fg(Color::Red),
+            ),
+            None if app.write_files => (
+                format!("Export {} as <prefix>-<message>.<extension>, prefix", what),
+                Style::default().fg(Color::Green),
+            ),
+            None if app.export_format.has_plaintexts() => (
+                format!(
+                    "Export {} with unknown bytes as '{}' to",
+                    what, app.unknown_marker
+                ),
+                Style::default().fg(Color::Green),
+            ),
+            None => (
+                format!("Export {} to", what),
+                Style::default().fg(Color::Green),
+            ),
+        };
+        let input = Paragraph::new(app.input.as_ref())
+            .style(style)
+            .block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(Clear, chunks[2]);
+        f.render_widget(input, chunks[2]);
+    }
+
+    if let InputMode::Search = app.input_mode {
+        let (title, style) = match &app.search_error {
+            Some(err) => (
+                format!(
+                    "Search ({}): {}",
+                    app.search_mode.name(),
+                    err.lines().last().unwrap_or_default()
+                ),
+                Style::default().fg(Color::Red),
+            ),
+            None => (
+                format!(
+                    "Search ({}): {} matches",
+                    app.search_mode.name(),
+                    app.search_matches.len()
+                ),
+                Style::default().fg(Color::Green),
+            ),
+        };
+        let input = Paragraph::new(app.search_pattern.as_ref())
+            .style(style)
+            .block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(Clear, chunks[2]);
+        f.render_widget(input, chunks[2]);
+    }
+
+    if let InputMode::Hunt = app.input_mode {
+        let (title, style) = match &app.hunt_error {
+            Some(err) => (
+                format!("Flag pattern: {}", err.lines().last().unwrap_or_default()),
+                Style::default().fg(Color::Red),
+            ),
+            None => (
+                format!("Flag pattern: {} candidates", app.cribs.len()),
+                Style::default().fg(Color::Green),
+            ),
+        };
+        let input = Paragraph::new(app.flag_pattern.as_ref())
+            .style(style)
+            .block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(Clear, chunks[2]);
+        f.render_widget(input, chunks[2]);
+    }
+
+    if let InputMode::Hunt | InputMode::Signatures = app.input_mode {
+        // Ranked candidates over the lower half of the messages
+        let area = list_popup_area(chunks[1], app.cribs.len());
+        let cribs: Vec<ListItem> = app
+            .cribs
+            .iter()
+            .enumerate()
+            .map(|(rank, crib)| {
+                let score = match app.input_mode {
+                    InputMode::Signatures => format!("{} known bytes agree", crib.confirmed),
+                    _ => format!("{:.2} bits per character", crib.score),
+                };
+                ListItem::new(format!(
+                    "{:>3}. message {} at {}: {}  {}, {} new key bytes",
+                    rank + 1,
+                    crib.message,
+                    crib.offset,
+                    crib.label,
+                    score,
+                    crib.key.len()
+                ))
+            })
+            .collect();
+        let mut state = ListState::default();
+        state.select(Some(app.crib_index));
+        let cribs = List::new(cribs)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Crib candidates"),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_widget(Clear, area);
+        f.render_stateful_widget(cribs, area, &mut state);
+    }
+
+    if let InputMode::Patterns = app.input_mode {
+        let area = list_popup_area(chunks[1], app.patterns.len());
+        let patterns: Vec<ListItem> = app
+            .patterns
+            .iter()
+            .enumerate()
+            .map(|(rank, pattern)| {
+                ListItem::new(format!(
+                    "{:>3}. {}  {} known bytes agree, {} new key bytes",
+                    rank + 1,
+                    pattern.describe(),
+                    pattern.agreement,
+                    pattern.extension.len()
+                ))
+            })
+            .collect();
+        let mut state = ListState::default();
+        state.select(Some(app.pattern_index));
+        let patterns = List::new(patterns)
+            .block(Block::default().borders(Borders::ALL).title("Key patterns"))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_widget(Clear, area);
+        f.render_stateful_widget(patterns, area, &mut state);
+    }
+
     match app.input_mode {
-        InputMode::Normal => {}
-        InputMode::Editing => f.set_cursor(
-            chunks[1].x + app.input.width() as u16 + 1 + app.position.1 as u16,
-            chunks[1].y + 1 + app.position.0 as u16,
+        InputMode::KnownPlaintext
+        | InputMode::Export
+        | InputMode::Overwrite
+        | InputMode::Verify => {
+            f.set_cursor(chunks[2].x + 1 + app.input.width() as u16, chunks[2].y + 1)
+        }
+        InputMode::Search => f.set_cursor(
+            chunks[2].x + 1 + app.search_pattern.width() as u16,
+            chunks[2].y + 1,
+        ),
+        InputMode::Hunt => f.set_cursor(
+            chunks[2].x + 1 + app.flag_pattern.width() as u16,
+            chunks[2].y + 1,
+        ),
+        // The cursor marks the selected search match outside editing
+        InputMode::Normal if !app.search_matches.is_empty() => {
+            set_message_cursor(f, app, chunks[1], scroll)
+        }
+        InputMode::Normal | InputMode::BulkPaste | InputMode::Signatures | InputMode::Patterns => {}
+        InputMode::Editing => set_message_cursor(f, app, chunks[1], scroll),
+    }
+}
+
+// Index of the search match covering each column of each message
+fn search_match_columns(app: &App) -> Vec<Vec<Option<usize>>> {
+    let mut columns: Vec<Vec<Option<usize>>> = app
+        .encrypted_messages
+        .iter()
+        .map(|m| vec![None; m.len()])
+        .collect();
+    for (n, m) in app.search_matches.iter().enumerate() {
+        if let Some(row) = columns.get_mut(m.message) {
+            for column in row.iter_mut().take(m.end).skip(m.start) {
+                column.get_or_insert(n);
+            }
+        }
+    }
+    columns
+}
+
+fn set_message_cursor<B>(f: &mut Frame<B>, app: &App, area: Rect, scroll: usize)
+where
+    B: Backend,
+{
+    let y = area.y + 1 + (app.position.0 - scroll) as u16;
+    // Binary mode draws two hex digits per column
+    let x = match app.binary {
+        true => {
+            let label = app.position.0.to_string().len() + 2;
+            match app.position.1.checked_sub(label) {
+                Some(column) => label + 2 * column + app.hex_digit.is_some() as usize,
+                None => app.position.1,
+            }
+        }
+        false => app.input.width() + app.position.1,
+    };
+    f.set_cursor(area.x + 1 + x as u16, y)
+}
+
+const XOR_ROW_LEN: usize = 16;
+
+fn draw_xor_block<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
+where
+    B: Backend,
+{
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(1),
+                Constraint::Length(3),
+                Constraint::Min(1),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+
+    f.render_widget(Paragraph::new(Spans::from(hint_spans(app))), chunks[0]);
+
+    let xor = match app.xor_pair_bytes() {
+        Some(xor) => xor,
+        None => {
+            let empty = Paragraph::new("Add at least two encrypted messages to compare them.")
+                .block(Block::default().borders(Borders::ALL).title("XOR"));
+            f.render_widget(empty, chunks[1]);
+            return;
+        }
+    };
+    let spaces = get_space_indices(&xor);
+
+    let legend = Spans::from(vec![
+        Span::styled("00", xor_class_style(XorClass::Same)),
+        Span::raw(" same character  "),
+        Span::styled("xx", xor_class_style(XorClass::SpaceLetter)),
+        Span::raw(" space and letter  "),
+        Span::styled("xx", xor_class_style(XorClass::LetterPair)),
+        Span::raw(" two letters  "),
+        Span::styled("xx", xor_class_style(XorClass::Other)),
+        Span::raw(format!(
+            " other    {} spaces likely, column {}",
+            spaces.len(),
+            app.xor_column
+        )),
+    ]);
+    let title = format!(
+        "Message {} \u{2295} message {}",
+        app.xor_pair.0, app.xor_pair.1
+    );
+    let legend = Paragraph::new(legend).block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(legend, chunks[1]);
+
+    let rows: Vec<ListItem> = xor
+        .chunks(XOR_ROW_LEN)
+        .enumerate()
+        .map(|(row, bytes)| {
+            let mut spans = vec![Span::raw(format!("{:04x}  ", row * XOR_ROW_LEN))];
+            let mut glyphs = String::new();
+            for (i, byte) in bytes.iter().enumerate() {
+                let column = row * XOR_ROW_LEN + i;
+                let class = classify_xor(*byte);
+                let mut style = xor_class_style(class);
+                if column == app.xor_column {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+                spans.push(Span::styled(format!("{:02x}", byte), style));
+                spans.push(Span::raw(" "));
+                glyphs.push(match class {
+                    XorClass::Same => '=',
+                    XorClass::SpaceLetter => 's',
+                    XorClass::LetterPair => 'l',
+                    XorClass::Other => '.',
+                });
+            }
+            spans.push(Span::raw(" ".repeat(3 * (XOR_ROW_LEN - bytes.len()) + 1)));
+            spans.push(Span::styled(glyphs, Style::default().fg(Color::DarkGray)));
+            ListItem::new(Spans::from(spans))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(app.xor_column / XOR_ROW_LEN));
+    let rows = List::new(rows)
+        .block(Block::default().borders(Borders::ALL).title("XOR"))
+        .highlight_style(Style::default());
+    f.render_stateful_widget(rows, chunks[2], &mut state);
+}
+
+fn draw_statistics_block<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
+where
+    B: Backend,
+{
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(1),
+                Constraint::Length(5),
+                Constraint::Length(5),
+                Constraint::Min(1),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+
+    f.render_widget(Paragraph::new(Spans::from(hint_spans(app))), chunks[0]);
+
+    if app.key.is_empty() {
+        let empty = Paragraph::new("Add encrypted messages to see their statistics.")
+            .block(Block::default().borders(Borders::ALL).title("Statistics"));
+        f.render_widget(empty, chunks[1]);
+        return;
+    }
+
+    app.stats_column = app.stats_column.min(app.key.len() - 1);
+
+    // Show the window of columns that fits the sparklines and holds the cursor
+    let width = chunks[1].width.saturating_sub(2).max(1) as usize;
+    let start = app.stats_column / width * width;
+    let end = (start + width).min(app.key.len());
+    let stats: Vec<_> = (start..end).map(|j| app.column_stats(j)).collect();
+
+    let coverage: Vec<u64> = stats.iter().map(|s| s.coverage as u64).collect();
+    let coverage = Sparkline::default()
+        .block(Block::default().borders(Borders::ALL).title(format!(
+            "Coverage, columns {}-{}",
+            start,
+            end - 1
+        )))
+        .style(Style::default().fg(Color::Cyan))
+        .data(&coverage);
+    f.render_widget(coverage, chunks[1]);
+
+    let votes: Vec<u64> = stats.iter().map(|s| s.best_votes as u64).collect();
+    let votes = Sparkline::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Space votes of the best candidate"),
+        )
+        .style(Style::default().fg(Color::Green))
+        .data(&votes);
+    f.render_widget(votes, chunks[2]);
+
+    let bottom = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .split(chunks[3]);
+
+    let column = app.stats_column;
+    let selected = &stats[column - start];
+    let labels: Vec<String> = selected
+        .candidates
+        .iter()
+        .map(|(key, _)| format!("{:02x}", key))
+        .collect();
+    let bars: Vec<(&str, u64)> = labels
+        .iter()
+        .zip(&selected.candidates)
+        .map(|(label, (_, votes))| (label.as_str(), *votes as u64))
+        .collect();
+    let chart = BarChart::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("Candidate key bytes, column {}", column)),
+        )
+        .bar_width(4)
+        .bar_gap(2)
+        .bar_style(Style::default().fg(Color::Yellow))
+        .value_style(Style::default().fg(Color::Black).bg(Color::Yellow))
+        .data(&bars)
+        .max(selected.coverage.saturating_sub(1).max(1) as u64);
+    f.render_widget(chart, bottom[0]);
+
+    let key = match app.key[column] {
+        Some(k) => format!("{:02x} ({})", k, app.key_origin[column].name()),
+        None => "unknown".to_string(),
+    };
+    let entropy = match selected.entropy {
+        Some(bits) => format!("{:.2} bits per character", bits),
+        None => "-".to_string(),
+    };
+    let mut lines = vec![
+        Spans::from(format!("Coverage:  {} messages", selected.coverage)),
+        Spans::from(format!("Key byte:  {}", key)),
+        Spans::from(format!("Entropy:   {}", entropy)),
+        Spans::from(""),
+        Spans::from(Span::styled(
+            "Key   votes  score",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+    ];
+    let needed = selected.coverage.saturating_sub(1).max(1) as f64;
+    for (key, votes) in &selected.candidates {
+        let mut style = Style::default();
+        if app.key[column] == Some(*key) {
+            style = style.fg(Color::Green);
+        }
+        lines.push(Spans::from(Span::styled(
+            format!(
+                "{:02x}    {:>5}  {:>4.0}%",
+                key,
+                votes,
+                100.0 * *votes as f64 / needed
+            ),
+            style,
+        )));
+    }
+    let details = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Column {}", column)),
+    );
+    f.render_widget(details, bottom[1]);
+}
+
+fn xor_class_style(class: XorClass) -> Style {
+    match class {
+        XorClass::Same => Style::default().fg(Color::Cyan),
+        XorClass::SpaceLetter => Style::default()
+            .fg(Color::Green)
+            .add_modifier(Modifier::BOLD),
+        XorClass::LetterPair => Style::default(),
+        XorClass::Other => Style::default().fg(Color::DarkGray),
+    }
+}
+
+fn draw_forge_block<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
+where
+    B: Backend,
+{
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(1),
+                Constraint::Length(3),
+                Constraint::Length(4),
+                Constraint::Length(5),
+                Constraint::Min(1),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+
+    f.render_widget(Paragraph::new(Spans::from(hint_spans(app))), chunks[0]);
+
+    let editing = |field: ForgeField| {
+        matches!(app.input_mode, InputMode::Editing) && app.forge_field == field
+    };
+    let field_style = |field: ForgeField| match editing(field) {
+        true => Style::default().fg(Color::Green),
+        false => Style::default(),
+    };
+
+    let plaintext = match editing(ForgeField::Plaintext) {
+        true => app.input.clone(),
+        false => String::new(),
+    };
+    let plaintext = Paragraph::new(plaintext)
+        .style(field_style(ForgeField::Plaintext))
+        .block(Block::default().borders(Borders::ALL).title("Plaintext"));
+    f.render_widget(plaintext, chunks[1]);
+
+    let key = match editing(ForgeField::Key) {
+        true => app.input.clone(),
+        false => encode_hex(&app.forge_key),
+    };
+    let key = Paragraph::new(key)
+        .style(field_style(ForgeField::Key))
+        .wrap(Wrap { trim: true })
+        .block(Block::default().borders(Borders::ALL).title(format!(
+            "Key ({} bytes, grows as needed)",
+            app.forge_key.len()
+        )));
+    f.render_widget(key, chunks[2]);
+
+    let forged_plaintext = match editing(ForgeField::Forgery) {
+        true => app.input.clone(),
+        false => app.forge_plaintext.clone(),
+    };
+    let forged_hex: String = app
+        .forgery
+        .iter()
+        .map(|opt| match opt {
+            Some(byte) => format!("{:02x}", byte),
+            None => "__".to_string(),
+        })
+        .collect();
+    let mut forgery = vec![
+        Spans::from(vec![
+            Span::styled(
+                "Plaintext:  ",
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(forged_plaintext, field_style(ForgeField::Forgery)),
+        ]),
+        Spans::from(vec![
+            Span::styled(
+                "Ciphertext: ",
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(forged_hex),
+        ]),
+    ];
+    let forged: Option<Vec<u8>> = app.forgery.iter().copied().collect();
+    if let Some(forged) = forged.filter(|c| !c.is_empty() && c.len() <= app.forge_key.len()) {
+        let decrypted: String = forged
+            .iter()
+            .zip(&app.forge_key)
+            .map(|(c, k)| match c ^ k {
+                p if p == b' ' || p.is_ascii_graphic() => p as char,
+                _ => '?',
+            })
+            .collect();
+        forgery.push(Spans::from(vec![
+            Span::styled(
+                "True key:   ",
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(decrypted),
+        ]));
+    }
+    let forgery = Paragraph::new(forgery).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Forgery with the recovered key"),
+    );
+    f.render_widget(forgery, chunks[3]);
+
+    let messages: Vec<ListItem> = app
+        .encrypted_messages
+        .iter()
+        .enumerate()
+        .map(|(i, m)| ListItem::new(Span::raw(format!("{}:{}", i, encode_hex(m)))))
+        .collect();
+    let messages =
+        List::new(messages).block(Block::default().borders(Borders::ALL).title("Messages"));
+    f.render_widget(messages, chunks[4]);
+
+    if let InputMode::Editing = app.input_mode {
+        let (chunk, x_offset) = match app.forge_field {
+            ForgeField::Plaintext => (chunks[1], 0),
+            ForgeField::Key => (chunks[2], 0),
+            ForgeField::Forgery => (chunks[3], 12),
+        };
+        f.set_cursor(
+            chunk.x + 1 + x_offset + app.input.width() as u16,
+            chunk.y + 1,
+        );
+    }
+}
+
+// Title of the Key block, listing the candidates left by propagation for the
+// column under the cursor while editing, or saying there are none
+fn key_title(app: &App) -> String {
+    let msg_id = app.position.0;
+    let column = match app.input_mode {
+        InputMode::Editing => app.cursor_column(),
+        _ => None,
+    };
+    if let Some((j, _)) = app.pending_guess.first() {
+        let (valid, invalid) = app.pending_guess_report();
+        let bytes: String = app
+            .pending_guess
+            .iter()
+            .map(|(_, byte)| format!("{:02X}", byte))
+            .collect();
+        return format!(
+            "Preview key {} at column {}: {} other messages valid, {} invalid (Enter to set, Esc to discard)",
+            bytes, j, valid, invalid
+        );
+    }
+    match column.and_then(|j| app.candidates.get(j).map(|c| (j, c))) {
+        Some((j, _)) if app.contradictions.contains(&j) => {
+            format!("Key (column {}: no key byte fits its neighbours)", j)
+        }
+        Some((j, candidates)) if !candidates.is_empty() => {
+            let msg = &app.encrypted_messages[msg_id];
+            let plaintexts: String = candidates.iter().map(|k| (msg[j] ^ k) as char).collect();
+            format!(
+                "Key (column {}: {} candidates \"{}\")",
+                j,
+                candidates.len(),
+                plaintexts
+            )
+        }
+        _ => "Key".to_string(),
+    }
+}
+
+// One span per column of a decrypted message. A multi-byte UTF-8 character
+// whose key bytes are all known is drawn in its first column, and the other
+// columns pad it out so that the columns of all messages stay aligned.
+fn plain_spans(
+    cipher: &[u8],
+    key: &[Option<u8>],
+    key_origin: &[KeyOrigin],
+    alphabet: Alphabet,
+) -> Vec<Span<'static>> {
+    let mut spans = Vec::with_capacity(cipher.len());
+    let mut j = 0;
+    while j < cipher.len() {
+        let decoded = utf8_char(cipher, key, j)
+            .map(|(c, len)| (c, len, c.width().unwrap_or(0)))
+            .filter(|(_, len, width)| (1..=*len).contains(width));
+        match decoded {
+            Some((c, len, width)) => {
+                let style = origin_style(key_origin[j]);
+                spans.push(Span::styled(c.to_string(), style));
+                spans.extend((1..len).map(|n| match n < width {
+                    true => Span::raw(""),
+                    false => Span::styled(" ", style),
+                }));
+                j += len;
+            }
+            None => {
+                spans.push(plain_byte_span(classify(
+                    cipher[j],
+                    key[j],
+                    key_origin[j],
+                    alphabet,
+                )));
+                j += 1;
+            }
+        }
+    }
+    spans
+}
+
+// Two hex digits per column of a decrypted message, for binary plaintexts
+fn hex_spans(cipher: &[u8], key: &[Option<u8>], key_origin: &[KeyOrigin]) -> Vec<Span<'static>> {
+    cipher
+        .iter()
+        .zip(key)
+        .zip(key_origin)
+        .map(|((c, k), origin)| match k {
+            Some(k) => Span::styled(format!("{:02X}", c ^ k), origin_style(*origin)),
+            None => Span::styled("__", Style::default().fg(Color::DarkGray)),
+        })
+        .collect()
+}
+
+fn origin_style(origin: KeyOrigin) -> Style {
+    match origin {
+        KeyOrigin::Manual => Style::default().fg(Color::Cyan),
+        KeyOrigin::Guess => Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::DIM),
+        _ => Style::default(),
+    }
+}
+
+// Render a decrypted byte with a glyph and colour according to its classification
+fn plain_byte_span(plain_byte: PlainByte) -> Span<'static> {
+    match plain_byte {
+        PlainByte::Unknown => Span::styled("_", Style::default().fg(Color::DarkGray)),
+        PlainByte::NonPrintable(_) => {
+            Span::styled("\u{b7}", Style::default().fg(Color::White).bg(Color::Red))
+        }
+        PlainByte::OutsideAlphabet(c) => Span::styled(
+            (c as char).to_string(),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ),
+        PlainByte::Manual(c) => {
+            Span::styled((c as char).to_string(), Style::default().fg(Color::Cyan))
+        }
+        PlainByte::Guessed(c) => Span::styled(
+            (c as char).to_string(),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::DIM),
         ),
+        PlainByte::Valid(c) => Span::raw((c as char).to_string()),
     }
 }
@@ -6,3 +6,88 @@ pub fn decode_hex(s: &str) -> Result<Vec<u8>, ParseIntError> {
         .map(|i| u8::from_str_radix(&s[i..i + 2], 16))
         .collect()
 }
+
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Lines of hex text split into the decoded ones and the rejected ones, which
+// keep their line number, text and what is wrong with them
+#[derive(Default)]
+pub struct HexLines {
+    pub accepted: Vec<Vec<u8>>,
+    pub rejected: Vec<(usize, String, String)>,
+}
+
+// Index of the first character that keeps 'line' from being a hex string,
+// ignoring surrounding whitespace, and what is wrong with it
+pub fn hex_error(line: &str) -> Option<(usize, String)> {
+    let leading = line.chars().take_while(|c| c.is_whitespace()).count();
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+        return Some((0, "no hex digits".to_string()));
+    }
+    if let Some((i, c)) = trimmed
+        .chars()
+        .enumerate()
+        .find(|(_, c)| !c.is_ascii_hexdigit())
+    {
+        return Some((leading + i, format!("invalid hex digit {:?}", c)));
+    }
+    if trimmed.len() % 2 != 0 {
+        let last = leading + trimmed.len() - 1;
+        return Some((last, "odd number of hex digits".to_string()));
+    }
+    None
+}
+
+// Decode one line of hex, ignoring surrounding whitespace
+pub fn decode_hex_line(line: &str) -> Result<Vec<u8>, String> {
+    match hex_error(line) {
+        Some((_, err)) => Err(err),
+        None => decode_hex(line.trim()).map_err(|err| err.to_string()),
+    }
+}
+
+// Decode every non-empty line of 'text'
+pub fn decode_hex_lines(text: &str) -> HexLines {
+    let mut lines = HexLines::default();
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match decode_hex_line(line) {
+            Ok(bytes) => lines.accepted.push(bytes),
+            Err(err) => lines.rejected.push((i + 1, line.trim().to_string(), err)),
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_hex_lines() {
+        let lines = decode_hex_lines("00ff\n\n  abc\nzz\r\n1234 \n");
+        assert_eq!(lines.accepted, vec![vec![0x00, 0xff], vec![0x12, 0x34]]);
+        let rejected: Vec<(usize, &str)> = lines
+            .rejected
+            .iter()
+            .map(|(n, line, _)| (*n, line.as_str()))
+            .collect();
+        assert_eq!(rejected, vec![(3, "abc"), (4, "zz")]);
+        assert_eq!(lines.rejected[0].2, "odd number of hex digits");
+
+        assert_eq!(
+            hex_error(" 00fg"),
+            Some((4, "invalid hex digit 'g'".to_string()))
+        );
+        assert_eq!(
+            hex_error("abc "),
+            Some((2, "odd number of hex digits".to_string()))
+        );
+        assert_eq!(hex_error("c0ffee"), None);
+    }
+}